@@ -0,0 +1,88 @@
+use crate::models::AwfulNewsArticle;
+use crate::utils::stable_hash;
+use std::error::Error;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{debug, warn};
+
+/// Directory of earlier analyses, one `<key>.json` per (content, template, model)
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+/// Cache key for an article: whitespace-normalized content plus the template and model
+/// that produced the analysis, so editing either one invalidates old entries
+pub fn cache_key(content: &str, template_fingerprint: &str, model: &str) -> String {
+    let normalized = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    let input = format!("{}\0{}\0{}", normalized, template_fingerprint, model);
+    format!("{:016x}", stable_hash(input.as_bytes()))
+}
+
+impl AnalysisCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The stored analysis for `key`; unreadable entries count as misses
+    pub async fn load(&self, key: &str) -> Option<AwfulNewsArticle> {
+        let path = self.path_for(key);
+        let bytes = fs::read(&path).await.ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(article) => Some(article),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt analysis cache entry");
+                None
+            }
+        }
+    }
+
+    /// Write via a temp file + rename so a crash never leaves half an entry
+    pub async fn store(&self, key: &str, article: &AwfulNewsArticle) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.path_for(key);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(article)?).await?;
+        fs::rename(&tmp, &path).await?;
+        debug!(%key, "Cached analysis");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_normalizes_whitespace() {
+        let a = cache_key("Title: A\n\nBody  text.", "tpl", "qwen");
+        let b = cache_key("  Title: A Body text. ", "tpl", "qwen");
+        assert_eq!(a, b);
+        assert_ne!(a, cache_key("Title: A Body text!", "tpl", "qwen"));
+        assert_ne!(a, cache_key("Title: A Body text.", "tpl2", "qwen"));
+        assert_ne!(a, cache_key("Title: A Body text.", "tpl", "llama"));
+    }
+
+    #[tokio::test]
+    async fn test_store_and_load() {
+        let dir = std::env::temp_dir().join(format!("atn_analysis_cache_{}", std::process::id()));
+        let cache = AnalysisCache::new(&dir);
+        let article: AwfulNewsArticle = serde_json::from_str(
+            r#"{"source":null,"dateOfPublication":"2025-05-06","timeOfPublication":"14:30:00",
+                "title":"Cached","category":"Law & Justice","summaryOfNewsArticle":"S",
+                "keyTakeAways":[],"namedEntities":[],"importantDates":[],"importantTimeframes":[],
+                "tags":[],"content":null}"#,
+        )
+        .unwrap();
+
+        assert!(cache.load("missing").await.is_none());
+        cache.store("abc", &article).await.unwrap();
+        assert_eq!(cache.load("abc").await.unwrap().title, "Cached");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
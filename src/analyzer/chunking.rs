@@ -0,0 +1,251 @@
+use crate::models::AwfulNewsArticle;
+use itertools::Itertools;
+
+/// Rough English average for BPE tokenizers; deliberately a little pessimistic
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Header lines the scrapers prepend to `NewsArticle.content`
+pub(crate) const HEADER_PREFIXES: &[&str] = &["Published:", "Published(raw):", "Title:", "Authors:"];
+
+/// Cheap token estimate (no tokenizer available for arbitrary models)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Split scraped content into chunks of at most `max_tokens` each.
+///
+/// The leading `Published:` / `Title:` / `Authors:` paragraphs are repeated at the top of
+/// every chunk so each one is analyzed with its headline and date. Splits happen between
+/// paragraphs, then between sentences, and only mid-sentence as a last resort.
+pub fn split_into_chunks(content: &str, max_tokens: usize) -> Vec<String> {
+    let paragraphs: Vec<&str> = content.split("\n\n").map(str::trim).filter(|p| !p.is_empty()).collect();
+    let header_len = paragraphs
+        .iter()
+        .take_while(|p| HEADER_PREFIXES.iter().any(|h| p.starts_with(h)))
+        .count();
+    let header = paragraphs[..header_len].join("\n\n");
+
+    // Leave room for the header in every chunk, but never less than a quarter of the budget
+    let body_budget = max_tokens
+        .saturating_sub(estimate_tokens(&header) + 1)
+        .max(max_tokens / 4)
+        .max(1);
+
+    let mut pieces = Vec::new();
+    for paragraph in &paragraphs[header_len..] {
+        split_piece(paragraph, body_budget, &mut pieces);
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for piece in pieces {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(&piece) + 1 > body_budget {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    if header.is_empty() {
+        chunks
+    } else if chunks.is_empty() {
+        vec![header]
+    } else {
+        chunks.into_iter().map(|c| format!("{}\n\n{}", header, c)).collect()
+    }
+}
+
+/// Push `text` as one piece if it fits, otherwise split it by sentences / characters
+fn split_piece(text: &str, budget: usize, out: &mut Vec<String>) {
+    if estimate_tokens(text) <= budget {
+        out.push(text.to_string());
+        return;
+    }
+
+    let mut current = String::new();
+    for sentence in text.split_inclusive(['.', '!', '?']) {
+        if estimate_tokens(sentence) > budget {
+            if !current.is_empty() {
+                out.push(std::mem::take(&mut current));
+            }
+            let max_chars = budget * CHARS_PER_TOKEN;
+            for part in &sentence.chars().chunks(max_chars) {
+                out.push(part.collect::<String>());
+            }
+            continue;
+        }
+        if estimate_tokens(&current) + estimate_tokens(sentence) > budget {
+            out.push(std::mem::take(&mut current));
+        }
+        current.push_str(sentence);
+    }
+    if !current.trim().is_empty() {
+        out.push(current);
+    }
+}
+
+/// Input for the reduce step: the header plus each chunk's summary in order
+pub fn reduce_input(header_source: &str, parts: &[AwfulNewsArticle]) -> String {
+    let header = header_source
+        .split("\n\n")
+        .map(str::trim)
+        .take_while(|p| HEADER_PREFIXES.iter().any(|h| p.starts_with(h)))
+        .join("\n\n");
+    let summaries = parts.iter().map(|p| p.summaryOfNewsArticle.trim()).join("\n\n");
+    if header.is_empty() {
+        summaries
+    } else {
+        format!("{}\n\n{}", header, summaries)
+    }
+}
+
+/// Combine per-chunk analyses into one article.
+///
+/// Headline, publication date/time and category come from the reduce pass when it
+/// succeeded (else the first chunk / most common category, the earliest on a tie);
+/// entities, dates, timeframes, takeaways and tags are the union of every part.
+pub fn merge_chunks(parts: Vec<AwfulNewsArticle>, reduced: Option<AwfulNewsArticle>) -> Option<AwfulNewsArticle> {
+    let counts = parts.iter().map(|p| p.category.as_str()).counts();
+    let top = counts.values().copied().max().unwrap_or(0);
+    let category = parts
+        .iter()
+        .map(|p| &p.category)
+        .find(|c| counts[c.as_str()] == top)
+        .cloned();
+    let summary = parts.iter().map(|p| p.summaryOfNewsArticle.trim()).join("\n\n");
+
+    let mut parts = parts.into_iter();
+    let (mut merged, rest): (AwfulNewsArticle, Vec<AwfulNewsArticle>) = match reduced {
+        Some(reduced) => (reduced, parts.collect()),
+        None => {
+            let mut first = parts.next()?;
+            first.summaryOfNewsArticle = summary;
+            first.category = category.unwrap_or(first.category);
+            (first, parts.collect())
+        }
+    };
+
+    for part in rest {
+        if merged.title.trim().is_empty() {
+            merged.title = part.title;
+        }
+        merged.keyTakeAways.extend(part.keyTakeAways);
+        merged.namedEntities.extend(part.namedEntities);
+        merged.importantDates.extend(part.importantDates);
+        merged.importantTimeframes.extend(part.importantTimeframes);
+        merged.tags.extend(part.tags);
+    }
+    merged.tags = std::mem::take(&mut merged.tags).into_iter().unique().collect();
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(title: &str, category: &str, summary: &str, takeaway: &str) -> AwfulNewsArticle {
+        AwfulNewsArticle {
+            source: None,
+            dateOfPublication: "2025-05-06".to_string(),
+            timeOfPublication: "14:30:00".to_string(),
+            title: title.to_string(),
+            category: category.to_string(),
+            summaryOfNewsArticle: summary.to_string(),
+            keyTakeAways: vec![takeaway.to_string()],
+            namedEntities: vec![],
+            importantDates: vec![],
+            importantTimeframes: vec![],
+            tags: vec!["news".to_string()],
+            content: None,
+            publishedAt: None,
+            novelty: None,
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_short_content_is_one_chunk() {
+        let content = "Title: Short\n\nOne paragraph.";
+        assert_eq!(split_into_chunks(content, 1000), vec![content.to_string()]);
+    }
+
+    #[test]
+    fn test_chunks_repeat_header_and_respect_budget() {
+        let para = "Sentence one is here. Sentence two follows it.".repeat(4);
+        let content = format!("Published: 2025-05-06T14:30:00+00:00\n\nTitle: Long\n\n{0}\n\n{0}\n\n{0}", para);
+        let chunks = split_into_chunks(&content, 80);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("Published: 2025-05-06T14:30:00+00:00\n\nTitle: Long\n\n"));
+            assert!(estimate_tokens(chunk) <= 80, "chunk too long: {}", estimate_tokens(chunk));
+        }
+        // Nothing is lost
+        let body: String = chunks.iter().map(|c| c.split("\n\n").skip(2).join("")).join("");
+        assert_eq!(body.matches("Sentence one").count(), 12);
+    }
+
+    #[test]
+    fn test_unbreakable_text_is_hard_split() {
+        let chunks = split_into_chunks(&"x".repeat(100), 10);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| estimate_tokens(c) <= 10));
+    }
+
+    #[test]
+    fn test_merge_without_reduce() {
+        let merged = merge_chunks(
+            vec![
+                part("Headline", "Economy & Business", "First half.", "a"),
+                part("", "Conflict & War", "Second half.", "b"),
+                part("", "Economy & Business", "Third part.", "c"),
+            ],
+            None,
+        )
+        .unwrap();
+        assert_eq!(merged.title, "Headline");
+        assert_eq!(merged.category, "Economy & Business");
+        assert_eq!(merged.summaryOfNewsArticle, "First half.\n\nSecond half.\n\nThird part.");
+        assert_eq!(merged.keyTakeAways, vec!["a", "b", "c"]);
+        assert_eq!(merged.tags, vec!["news"]);
+
+        // A tie goes to the category seen first, every time
+        for _ in 0..20 {
+            let tied = vec![
+                part("H", "Law & Justice", "1", "a"),
+                part("", "Conflict & War", "2", "b"),
+                part("", "Conflict & War", "3", "c"),
+                part("", "Law & Justice", "4", "d"),
+            ];
+            assert_eq!(merge_chunks(tied, None).unwrap().category, "Law & Justice");
+        }
+    }
+
+    #[test]
+    fn test_merge_prefers_reduce_result() {
+        let reduced = part("Reduced headline", "Law & Justice", "Overall summary.", "overall");
+        let parts = vec![part("H", "Conflict & War", "1", "a"), part("H", "Conflict & War", "2", "b")];
+        let merged = merge_chunks(parts, Some(reduced)).unwrap();
+        assert_eq!(merged.title, "Reduced headline");
+        assert_eq!(merged.summaryOfNewsArticle, "Overall summary.");
+        assert_eq!(merged.keyTakeAways, vec!["overall", "a", "b"]);
+        assert!(merge_chunks(vec![], None).is_none());
+    }
+
+    #[test]
+    fn test_reduce_input() {
+        let input = reduce_input("Title: T\n\nBody", &[part("T", "c", "One.", "a"), part("T", "c", "Two.", "b")]);
+        assert_eq!(input, "Title: T\n\nOne.\n\nTwo.");
+    }
+}
@@ -0,0 +1,348 @@
+pub mod cache;
+pub mod chunking;
+pub mod validate;
+
+use crate::api::ask_with_backoff;
+use crate::models::{AwfulNewsArticle, ImportantDate, ImportantTimeframe, NamedEntity, NewsArticle};
+use crate::utils::{stable_hash, truncate_for_log};
+use awful_aj::{config::AwfulJadeConfig, template::ChatTemplate};
+use cache::AnalysisCache;
+use chunking::estimate_tokens;
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use tracing::{debug, error, info, instrument, warn};
+use validate::Problem;
+
+/// Turns scraped articles into `AwfulNewsArticle`s via the LLM, reusing cached analyses
+pub struct Analyzer {
+    config: AwfulJadeConfig,
+    template: ChatTemplate,
+    template_fingerprint: String,
+    cache: Option<AnalysisCache>,
+    /// Article tokens sent per request; longer articles are chunked
+    max_input_tokens: usize,
+    /// `response_format` schema responses are validated against
+    schema: Option<Value>,
+    max_reasks: usize,
+}
+
+/// Never chunk finer than this, whatever the config says
+const MIN_INPUT_TOKENS: usize = 256;
+
+impl Analyzer {
+    pub fn new(config: AwfulJadeConfig, template: ChatTemplate) -> Self {
+        // Any change to the prompt, examples or schema changes the fingerprint
+        let template_json = serde_json::to_string(&template).unwrap_or_default();
+        let template_fingerprint = format!("{:016x}", stable_hash(template_json.as_bytes()));
+        let max_input_tokens = default_input_budget(&config, &template);
+        let schema = validate::schema_from_template(&template);
+        if schema.is_none() {
+            warn!("Template has no response_format schema; responses will only be parsed, not validated");
+        }
+        Self {
+            config,
+            template,
+            template_fingerprint,
+            cache: None,
+            max_input_tokens,
+            schema,
+            max_reasks: 1,
+        }
+    }
+
+    /// Override the computed per-request article budget
+    pub fn with_max_input_tokens(mut self, max_input_tokens: Option<usize>) -> Self {
+        if let Some(max) = max_input_tokens {
+            self.max_input_tokens = max.max(MIN_INPUT_TOKENS);
+        }
+        self
+    }
+
+    /// Re-asks allowed per request when the response fails schema validation
+    pub fn with_max_reasks(mut self, max_reasks: usize) -> Self {
+        self.max_reasks = max_reasks;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<AnalysisCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Analyze one article; `None` if the model never produced usable JSON
+    #[instrument(level = "info", skip(self, article), fields(source = %article.source))]
+    pub async fn analyze(&self, index: usize, article: &NewsArticle) -> Option<AwfulNewsArticle> {
+        let key = cache::cache_key(&article.content, &self.template_fingerprint, &self.config.model);
+
+        if let Some(cache) = &self.cache
+            && let Some(mut cached) = cache.load(&key).await
+        {
+            info!(index, %key, "Analysis cache hit; skipping LLM call");
+            cached.source = Some(article.source.clone());
+            cached.content = Some(article.content.clone());
+            cached.normalize_published_at(article.published_at);
+            return Some(cached);
+        }
+
+        let known = known_fields(article);
+        let tokens = estimate_tokens(&article.content);
+        let mut analysis = if tokens <= self.max_input_tokens {
+            self.ask(index, &article.content, &known).await?
+        } else {
+            info!(index, tokens, budget = self.max_input_tokens, "Article exceeds token budget; analyzing in chunks");
+            self.ask_chunked(index, &article.content, &known).await?
+        };
+        dedupe(&mut analysis);
+
+        if let Some(cache) = &self.cache
+            && let Err(e) = cache.store(&key, &analysis).await
+        {
+            warn!(index, error = %e, "Failed to write analysis cache entry");
+        }
+
+        analysis.source = Some(article.source.clone());
+        analysis.content = Some(article.content.clone());
+        analysis.normalize_published_at(article.published_at);
+        info!(index, "Successfully processed article");
+        Some(analysis)
+    }
+
+    /// Map-reduce: analyze each chunk, then summarize the chunk summaries and merge
+    async fn ask_chunked(&self, index: usize, content: &str, known: &Map<String, Value>) -> Option<AwfulNewsArticle> {
+        let chunks = chunking::split_into_chunks(content, self.max_input_tokens);
+        let mut parts = Vec::with_capacity(chunks.len());
+        for (n, chunk) in chunks.iter().enumerate() {
+            debug!(index, chunk = n, of = chunks.len(), tokens = estimate_tokens(chunk), "Analyzing chunk");
+            match self.ask(index, chunk, known).await {
+                Some(part) => parts.push(part),
+                None => warn!(index, chunk = n, "Chunk analysis failed; merging without it"),
+            }
+        }
+
+        let reduced = if parts.len() > 1 {
+            let input = chunking::reduce_input(content, &parts);
+            if estimate_tokens(&input) <= self.max_input_tokens {
+                self.ask(index, &input, known).await
+            } else {
+                warn!(index, "Chunk summaries exceed token budget; skipping reduce pass");
+                None
+            }
+        } else {
+            None
+        };
+
+        let merged = chunking::merge_chunks(parts, reduced);
+        if merged.is_none() {
+            warn!(index, "No chunk could be analyzed; skipping article");
+        }
+        merged
+    }
+
+    /// Ask the model about `text`, repairing minor schema violations and re-asking with
+    /// the validation errors (or once on truncation) before giving up. Fields in `known`
+    /// override the model's answer.
+    async fn ask(&self, index: usize, text: &str, known: &Map<String, Value>) -> Option<AwfulNewsArticle> {
+        debug!(index, "Analyzing article");
+
+        let mut prompt = text.to_string();
+        let mut retried_truncation = false;
+        let mut reasks = 0;
+        loop {
+            let response_json = match ask_with_backoff(&self.config, &prompt, &self.template).await {
+                Ok(r) => r,
+                Err(e) => {
+                    error!(index, error = %e, "API call failed; skipping article");
+                    return None;
+                }
+            };
+
+            match validate::check_response::<AwfulNewsArticle>(&response_json, self.schema.as_ref(), known) {
+                Ok((analysis, repairs)) => {
+                    if !repairs.is_empty() {
+                        info!(index, ?repairs, "Repaired model output");
+                    }
+                    return Some(analysis);
+                }
+                // Truncation is a length problem, not a content one: same prompt again, once
+                Err(Problem::Truncated(e)) if !retried_truncation => {
+                    warn!(index, error = %e, "EOF while parsing; re-asking once");
+                    retried_truncation = true;
+                }
+                Err(Problem::Invalid(errors)) if reasks < self.max_reasks => {
+                    reasks += 1;
+                    warn!(index, attempt = reasks, ?errors, "Response failed schema validation; re-asking");
+                    prompt = reask_prompt(text, &errors);
+                }
+                Err(problem) => {
+                    warn!(
+                        index,
+                        ?problem,
+                        response_preview = %truncate_for_log(&response_json, 300),
+                        "Model returned non-conforming JSON; skipping article"
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Fields the scraper already knows, so a garbled answer for them cannot sink the article:
+/// the publication date and time, in the publication's own offset
+fn known_fields(article: &NewsArticle) -> Map<String, Value> {
+    let Some(at) = article.published_at else {
+        return Map::new();
+    };
+    Map::from_iter([
+        ("dateOfPublication".to_string(), at.format("%Y-%m-%d").to_string().into()),
+        ("timeOfPublication".to_string(), at.format("%H:%M:%S").to_string().into()),
+    ])
+}
+
+/// The original input followed by what was wrong with the last answer
+fn reask_prompt(text: &str, errors: &[String]) -> String {
+    format!(
+        "{}\n\nYour previous answer did not match the required JSON schema:\n- {}\nReturn the corrected JSON only.",
+        text,
+        errors.join("\n- ")
+    )
+}
+
+/// Tokens left for the article once the prompt and the response are accounted for
+fn default_input_budget(config: &AwfulJadeConfig, template: &ChatTemplate) -> usize {
+    let context = config.context_max_tokens as usize;
+    let response = config.assistant_minimum_context_tokens.max(0) as usize;
+    let prompt = estimate_tokens(&template.system_prompt)
+        + estimate_tokens(&serde_json::to_string(&template.messages).unwrap_or_default());
+    context.saturating_sub(response + prompt).max(MIN_INPUT_TOKENS)
+}
+
+/// Drop repeated entities, dates, timeframes and takeaways
+pub fn dedupe(article: &mut AwfulNewsArticle) {
+    article.namedEntities = std::mem::take(&mut article.namedEntities)
+        .into_iter()
+        .unique_by(|e| e.name.clone())
+        .collect::<Vec<NamedEntity>>();
+    article.importantDates = std::mem::take(&mut article.importantDates)
+        .into_iter()
+        .unique_by(|e| e.descriptionOfWhyDateIsRelevant.clone())
+        .collect::<Vec<ImportantDate>>();
+    article.importantTimeframes = std::mem::take(&mut article.importantTimeframes)
+        .into_iter()
+        .unique_by(|e| e.descriptionOfWhyTimeFrameIsRelevant.clone())
+        .collect::<Vec<ImportantTimeframe>>();
+    article.keyTakeAways = std::mem::take(&mut article.keyTakeAways)
+        .into_iter()
+        .unique()
+        .collect::<Vec<String>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{article_json, news_parser_template, MockLlm, Reply};
+
+    fn news(content: &str) -> NewsArticle {
+        NewsArticle {
+            source: "https://example.com/story".to_string(),
+            content: content.to_string(),
+            published_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_response_is_reasked() {
+        let valid = article_json("Title: Cut").to_string();
+        let mock = MockLlm::echo_articles().await.script([Reply::truncated(valid, 40)]);
+        let analyzer = Analyzer::new(mock.config(), news_parser_template());
+
+        let analysis = analyzer.analyze(0, &news("Title: Cut\n\nBody.")).await.unwrap();
+        assert_eq!(analysis.title, "Cut");
+        assert_eq!(analysis.source.as_deref(), Some("https://example.com/story"));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].user(), requests[1].user());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_response_is_reasked_with_errors() {
+        let mut invalid = article_json("Title: Bad");
+        invalid["category"] = "Sports".into();
+        let mock = MockLlm::echo_articles().await.script([Reply::content(invalid.to_string())]);
+        let analyzer = Analyzer::new(mock.config(), news_parser_template());
+
+        let analysis = analyzer.analyze(0, &news("Title: Bad\n\nBody.")).await.unwrap();
+        assert_eq!(analysis.category, "Politics & Governance");
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].user().starts_with("Title: Bad\n\nBody."));
+        assert!(requests[1].user().contains("/category: \"Sports\" is not one of"));
+    }
+
+    #[tokio::test]
+    async fn test_scraped_publication_time_overrides_garbled_answer() {
+        let mut garbled = article_json("Title: Dated");
+        garbled["dateOfPublication"] = "sometime last week".into();
+        garbled["timeOfPublication"] = "noonish".into();
+        let mock = MockLlm::start(move |_| Reply::content(garbled.to_string())).await;
+        let analyzer = Analyzer::new(mock.config(), news_parser_template()).with_max_reasks(0);
+        let article = NewsArticle {
+            published_at: Some(chrono::DateTime::parse_from_rfc3339("2025-05-06T14:30:00-04:00").unwrap()),
+            ..news("Title: Dated\n\nBody.")
+        };
+
+        let analysis = analyzer.analyze(0, &article).await.unwrap();
+        assert_eq!(analysis.dateOfPublication, "2025-05-06");
+        assert_eq!(analysis.timeOfPublication, "14:30:00");
+        assert_eq!(analysis.publishedAt.map(|at| at.to_rfc3339()).as_deref(), Some("2025-05-06T14:30:00-04:00"));
+        assert_eq!(mock.requests().len(), 1);
+        // Without a scraped time the same answer is still rejected
+        assert!(analyzer.analyze(0, &news("Title: Dated\n\nBody.")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_reasks() {
+        let mock = MockLlm::start(|_| Reply::content("not json at all")).await;
+        let analyzer = Analyzer::new(mock.config(), news_parser_template()).with_max_reasks(2);
+
+        assert!(analyzer.analyze(0, &news("Body.")).await.is_none());
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_long_article_is_chunked() {
+        let mock = MockLlm::echo_articles().await;
+        let analyzer = Analyzer::new(mock.config(), news_parser_template()).with_max_input_tokens(Some(256));
+        let body = "A sentence about the story that keeps going. ".repeat(60);
+        let content = format!("Title: Long story\n\n{0}\n\n{0}", body);
+
+        let analysis = analyzer.analyze(0, &news(&content)).await.unwrap();
+        assert_eq!(analysis.title, "Long story");
+        // Several chunks plus the reduce pass
+        assert!(mock.requests().len() >= 4, "{} requests", mock.requests().len());
+        assert_eq!(analysis.namedEntities.len(), 1);
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let mut article: AwfulNewsArticle = serde_json::from_str(
+            r#"{"source":null,"dateOfPublication":"2025-05-06","timeOfPublication":"14:30:00",
+                "title":"T","category":"Law & Justice","summaryOfNewsArticle":"S",
+                "keyTakeAways":["a","a","b"],
+                "namedEntities":[
+                    {"name":"UN","whatIsThisEntity":"x","whyIsThisEntityRelevantToTheArticle":"y"},
+                    {"name":"UN","whatIsThisEntity":"x2","whyIsThisEntityRelevantToTheArticle":"y2"}],
+                "importantDates":[
+                    {"dateMentionedInArticle":"2025-05-06","descriptionOfWhyDateIsRelevant":"vote"},
+                    {"dateMentionedInArticle":"2025-05-07","descriptionOfWhyDateIsRelevant":"vote"}],
+                "importantTimeframes":[],"tags":[],"content":null}"#,
+        )
+        .unwrap();
+
+        dedupe(&mut article);
+        assert_eq!(article.keyTakeAways, vec!["a", "b"]);
+        assert_eq!(article.namedEntities.len(), 1);
+        assert_eq!(article.namedEntities[0].whatIsThisEntity, "x");
+        assert_eq!(article.importantDates.len(), 1);
+    }
+}
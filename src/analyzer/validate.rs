@@ -0,0 +1,492 @@
+use crate::utils::looks_truncated;
+use awful_aj::template::ChatTemplate;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// Why a model response could not be used
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The JSON ended early (usually the response hit the token limit)
+    Truncated(String),
+    /// Parse or schema errors, phrased so they can be sent back to the model
+    Invalid(Vec<String>),
+}
+
+/// The JSON Schema in the template's `response_format`, if it has one
+pub fn schema_from_template(template: &ChatTemplate) -> Option<Value> {
+    let format = serde_json::to_value(&template.response_format).ok()?;
+    [
+        format.get("schema"),
+        format.get("json_schema").and_then(|j| j.get("schema")),
+        Some(&format).filter(|f| f.get("type").is_some()),
+    ]
+    .into_iter()
+    .flatten()
+    .next()
+    .cloned()
+}
+
+/// Clean up, repair and validate a raw model response, then deserialize it. Top-level
+/// fields in `known` replace whatever the model answered before anything is checked.
+/// On success also returns the repairs that were applied.
+pub fn check_response<T: DeserializeOwned>(
+    raw: &str,
+    schema: Option<&Value>,
+    known: &Map<String, Value>,
+) -> Result<(T, Vec<String>), Problem> {
+    let mut repairs = Vec::new();
+    let cleaned = repair_text(raw);
+    if cleaned != raw.trim() {
+        repairs.push("removed code fences / surrounding text / trailing commas".to_string());
+    }
+
+    let mut value: Value = match serde_json::from_str(&cleaned) {
+        Ok(v) => v,
+        Err(e) if looks_truncated(&e) => return Err(Problem::Truncated(e.to_string())),
+        Err(e) => return Err(Problem::Invalid(vec![format!("response is not valid JSON: {}", e)])),
+    };
+
+    if let Value::Object(fields) = &mut value {
+        for (key, known) in known {
+            if fields.get(key) != Some(known) {
+                repairs.push(format!("/{}: replaced {} with the known value", key, fields.get(key).unwrap_or(&Value::Null)));
+                fields.insert(key.clone(), known.clone());
+            }
+        }
+    }
+
+    if let Some(schema) = schema {
+        repair_value(schema, &mut value, "", &mut repairs);
+        let errors = validate(schema, &value);
+        if !errors.is_empty() {
+            return Err(Problem::Invalid(errors));
+        }
+    }
+
+    serde_json::from_value(value)
+        .map(|parsed| (parsed, repairs))
+        .map_err(|e| Problem::Invalid(vec![e.to_string()]))
+}
+
+/* -------------------- TEXT REPAIRS -------------------- */
+
+/// Strip Markdown code fences and prose around the object, and drop trailing commas
+pub fn repair_text(raw: &str) -> String {
+    let mut text = raw.trim();
+
+    if let Some(rest) = text.strip_prefix("```") {
+        // Drop the fence line (which may carry a language tag) and the closing fence
+        text = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+        text = text.trim_end().strip_suffix("```").unwrap_or(text).trim();
+    }
+
+    if let Some(start) = text.find(['{', '[']) {
+        text = match text.rfind(['}', ']']) {
+            Some(end) if end > start => &text[start..=end],
+            _ => &text[start..],
+        };
+    }
+
+    strip_trailing_commas(text)
+}
+
+/// Remove commas directly before `}` or `]`, leaving string contents alone
+fn strip_trailing_commas(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|n| !n.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/* -------------------- VALUE REPAIRS -------------------- */
+
+/// Fix minor schema violations in place: snap near-miss enum values, coerce
+/// date/time formats, fill missing required arrays. Records what it changed.
+pub fn repair_value(schema: &Value, value: &mut Value, path: &str, repairs: &mut Vec<String>) {
+    if let (Some(options), Some(s)) = (schema.get("enum").and_then(Value::as_array), value.as_str()) {
+        let options: Vec<&str> = options.iter().filter_map(Value::as_str).collect();
+        if !options.contains(&s)
+            && let Some(snapped) = snap_to_enum(s, &options)
+        {
+            repairs.push(format!("{}: {:?} -> {:?}", display_path(path), s, snapped));
+            *value = Value::String(snapped.to_string());
+        }
+    }
+
+    if let (Some(format), Some(s)) = (schema.get("format").and_then(Value::as_str), value.as_str())
+        && !format_ok(format, s)
+        && let Some(fixed) = coerce_format(format, s)
+    {
+        repairs.push(format!("{}: {:?} -> {:?}", display_path(path), s, fixed));
+        *value = Value::String(fixed);
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for key in required(schema) {
+                let is_array = properties
+                    .and_then(|p| p.get(key))
+                    .and_then(|s| s.get("type"))
+                    .and_then(Value::as_str)
+                    == Some("array");
+                if is_array && !map.contains_key(key) {
+                    repairs.push(format!("{}/{}: missing; defaulted to []", path, key));
+                    map.insert(key.to_string(), Value::Array(Vec::new()));
+                }
+            }
+            if let Some(properties) = properties {
+                for (key, child) in map.iter_mut() {
+                    if let Some(child_schema) = properties.get(key) {
+                        repair_value(child_schema, child, &format!("{}/{}", path, key), repairs);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter_mut().enumerate() {
+                    repair_value(item_schema, item, &format!("{}/{}", path, i), repairs);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Map a near-miss (case, punctuation, "and" for "&", a prefix, or a typo) onto an enum value
+pub fn snap_to_enum<'a>(value: &str, options: &[&'a str]) -> Option<&'a str> {
+    let wanted = normalize(value);
+    if wanted.is_empty() {
+        return None;
+    }
+    let normalized: Vec<(String, &str)> = options.iter().map(|o| (normalize(o), *o)).collect();
+
+    if let Some((_, o)) = normalized.iter().find(|(n, _)| *n == wanted) {
+        return Some(o);
+    }
+
+    let containing: Vec<&str> = normalized
+        .iter()
+        .filter(|(n, _)| n.starts_with(&wanted) || wanted.starts_with(n.as_str()))
+        .map(|(_, o)| *o)
+        .collect();
+    if let [only] = containing.as_slice() {
+        return Some(only);
+    }
+
+    normalized
+        .iter()
+        .map(|(n, o)| (levenshtein(n, &wanted), *o))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, o)| o)
+}
+
+fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .replace(" and ", "&")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '&')
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = (prev + usize::from(ca != *cb)).min(row[j] + 1).min(cur + 1);
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+fn coerce_format(format: &str, s: &str) -> Option<String> {
+    let s = s.trim();
+    let fixed = match format {
+        // "2025-05-06T14:30:00Z" in a date field
+        "date" => s.get(..10).map(str::to_string),
+        // "2025-05-06T14:30:00Z" or "14:30" in a time field
+        "time" => match s.split_once('T') {
+            Some((_, time)) => Some(time.to_string()),
+            None if s.len() == 5 => Some(format!("{}:00", s)),
+            None => None,
+        },
+        _ => None,
+    }?;
+    format_ok(format, &fixed).then_some(fixed)
+}
+
+/* -------------------- VALIDATION -------------------- */
+
+/// Check `value` against the JSON Schema subset the templates use: `type`,
+/// `properties`, `required`, `items`, `enum` and `format` (date, time, date-time).
+/// Returns one human-readable error per violation.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let at = display_path(path);
+
+    if let Some(expected) = schema.get("type").and_then(Value::as_str)
+        && !type_matches(expected, value)
+    {
+        errors.push(format!("{}: expected {}, got {}", at, expected, type_name(value)));
+        return;
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        let list = options.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(", ");
+        errors.push(format!("{}: {} is not one of: {}", at, value, list));
+    }
+
+    if let (Some(format), Some(s)) = (schema.get("format").and_then(Value::as_str), value.as_str())
+        && !format_ok(format, s)
+    {
+        errors.push(format!("{}: {:?} is not a valid {} ({})", at, s, format, format_hint(format)));
+    }
+
+    match value {
+        Value::Object(map) => {
+            for key in required(schema) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: missing required property {:?}", at, key));
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (key, child_schema) in properties {
+                    if let Some(child) = map.get(key) {
+                        validate_at(child_schema, child, &format!("{}/{}", path, key), errors);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}/{}", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn required(schema: &Value) -> impl Iterator<Item = &str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Formats follow RFC 3339; `time` also accepts a missing offset, as the models usually omit it
+fn format_ok(format: &str, s: &str) -> bool {
+    match format {
+        "date" => NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok(),
+        "time" => {
+            let offset_start = s.find(['Z', 'z', '+', '-']).unwrap_or(s.len());
+            let (time, offset) = s.split_at(offset_start);
+            let time_ok = NaiveTime::parse_from_str(time, "%H:%M:%S%.f").is_ok();
+            let offset_ok = matches!(offset, "" | "Z" | "z")
+                || (offset.len() == 6 && NaiveTime::parse_from_str(&format!("{}:00", &offset[1..]), "%H:%M:%S").is_ok());
+            time_ok && offset_ok
+        }
+        "date-time" => DateTime::parse_from_rfc3339(s).is_ok(),
+        _ => true,
+    }
+}
+
+fn format_hint(format: &str) -> &'static str {
+    match format {
+        "date" => "YYYY-MM-DD",
+        "time" => "HH:MM:SS",
+        "date-time" => "RFC 3339",
+        _ => "see schema",
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AwfulNewsArticle;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        let template: serde_yaml::Value = serde_yaml::from_str(include_str!("../../template/news_parser.yaml")).unwrap();
+        serde_json::to_value(&template["response_format"]["schema"]).unwrap()
+    }
+
+    fn valid() -> Value {
+        json!({
+            "title": "Ship sinks before drill",
+            "dateOfPublication": "2025-05-06",
+            "timeOfPublication": "02:03:00.000Z",
+            "category": "Conflict & War",
+            "summaryOfNewsArticle": "A ship sank.",
+            "namedEntities": [{"name": "USS Brattleboro", "whatIsThisEntity": "A ship", "whyIsThisEntityRelevantToTheArticle": "It sank"}],
+            "keyTakeAways": ["It sank"],
+            "importantDates": [{"dateMentionedInArticle": "2025-04-28", "descriptionOfWhyDateIsRelevant": "Sinking"}],
+            "importantTimeframes": [],
+            "tags": ["navy"]
+        })
+    }
+
+    #[test]
+    fn test_valid_response_passes() {
+        assert!(validate(&schema(), &valid()).is_empty());
+        let (article, repairs) = check_response::<AwfulNewsArticle>(&valid().to_string(), Some(&schema()), &Map::new()).unwrap();
+        assert_eq!(article.category, "Conflict & War");
+        assert!(repairs.is_empty());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let mut v = valid();
+        v["category"] = json!("Sports");
+        v["dateOfPublication"] = json!("May 6, 2025");
+        v["tags"] = json!("navy");
+        v.as_object_mut().unwrap().remove("title");
+        v["namedEntities"][0].as_object_mut().unwrap().remove("name");
+
+        let errors = validate(&schema(), &v);
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("/category: \"Sports\" is not one of: Politics & Governance")));
+        assert!(errors.contains(&"/dateOfPublication: \"May 6, 2025\" is not a valid date (YYYY-MM-DD)".to_string()));
+        assert!(errors.contains(&"/tags: expected array, got string".to_string()));
+        assert!(errors.contains(&"/: missing required property \"title\"".to_string()));
+        assert!(errors.contains(&"/namedEntities/0: missing required property \"name\"".to_string()));
+    }
+
+    #[test]
+    fn test_repair_text() {
+        assert_eq!(repair_text("```json\n{\"a\": [1, 2,],}\n```"), "{\"a\": [1, 2]}");
+        assert_eq!(repair_text("Here is the JSON:\n{\"a\": \"x,}\"}\nThanks!"), "{\"a\": \"x,}\"}");
+        assert_eq!(repair_text("{\"a\": \"b\\\",\"}"), "{\"a\": \"b\\\",\"}");
+    }
+
+    #[test]
+    fn test_repairs_make_response_valid() {
+        let mut v = valid();
+        v["category"] = json!("economy and business");
+        v["dateOfPublication"] = json!("2025-05-06T02:03:00Z");
+        v["timeOfPublication"] = json!("02:03");
+        v.as_object_mut().unwrap().remove("importantTimeframes");
+        let raw = format!("```json\n{}\n```", v);
+
+        let (article, repairs) = check_response::<AwfulNewsArticle>(&raw, Some(&schema()), &Map::new()).unwrap();
+        assert_eq!(article.category, "Economy & Business");
+        assert_eq!(article.dateOfPublication, "2025-05-06");
+        assert_eq!(article.timeOfPublication, "02:03:00");
+        assert!(article.importantTimeframes.is_empty());
+        assert_eq!(repairs.len(), 5, "{:?}", repairs);
+    }
+
+    #[test]
+    fn test_known_fields_replace_the_answer() {
+        let mut v = valid();
+        v["dateOfPublication"] = json!("yesterday");
+        v["timeOfPublication"] = json!("14:30:00");
+        let known = Map::from_iter([
+            ("dateOfPublication".to_string(), json!("2025-05-06")),
+            ("timeOfPublication".to_string(), json!("14:30:00")),
+        ]);
+
+        let (article, repairs) = check_response::<AwfulNewsArticle>(&v.to_string(), Some(&schema()), &known).unwrap();
+        assert_eq!(article.dateOfPublication, "2025-05-06");
+        assert_eq!(repairs, vec!["/dateOfPublication: replaced \"yesterday\" with the known value".to_string()]);
+    }
+
+    #[test]
+    fn test_check_response_problems() {
+        assert!(matches!(
+            check_response::<AwfulNewsArticle>("{\"title\": \"cut off", Some(&schema()), &Map::new()),
+            Err(Problem::Truncated(_))
+        ));
+        let mut v = valid();
+        v["category"] = json!("Sports");
+        match check_response::<AwfulNewsArticle>(&v.to_string(), Some(&schema()), &Map::new()) {
+            Err(Problem::Invalid(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("unexpected {:?}", other.map(|(a, _)| a.title)),
+        }
+    }
+
+    #[test]
+    fn test_snap_to_enum() {
+        let options = ["Politics & Governance", "Science & Technology", "Special Interest / Other"];
+        assert_eq!(snap_to_enum("politics and governance", &options), Some("Politics & Governance"));
+        assert_eq!(snap_to_enum("Science", &options), Some("Science & Technology"));
+        assert_eq!(snap_to_enum("Sciense & Technology", &options), Some("Science & Technology"));
+        assert_eq!(snap_to_enum("Special Interest/Other", &options), Some("Special Interest / Other"));
+        assert_eq!(snap_to_enum("Sports", &options), None);
+    }
+
+    #[test]
+    fn test_time_format() {
+        assert!(format_ok("time", "14:30:00"));
+        assert!(format_ok("time", "02:03:00.000Z"));
+        assert!(format_ok("time", "14:30:00+02:00"));
+        assert!(!format_ok("time", "14:30"));
+        assert!(!format_ok("time", "25:00:00"));
+    }
+}
@@ -8,6 +8,8 @@ use tokio::time::sleep;
 use tracing::{error, info, instrument, warn};
 
 /// Trait for async LLM interaction
+// Futures are only ever awaited on the current task, so no `Send` bound is needed
+#[allow(async_fn_in_trait)]
 pub trait AskAsync {
     type Response;
     async fn ask(&self, text: &str) -> Result<Self::Response, Box<dyn Error>>;
@@ -131,7 +133,7 @@ impl<'a> AskAsync for AskFnWrapper<'a> {
 #[instrument(level = "info", skip_all)]
 pub async fn ask_with_backoff(
     config: &AwfulJadeConfig,
-    article: &String,
+    article: &str,
     template: &ChatTemplate,
 ) -> Result<String, Box<dyn Error>> {
     let t0 = Instant::now();
@@ -151,3 +153,29 @@ pub async fn ask_with_backoff(
     }
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{news_parser_template, MockLlm, Reply};
+
+    #[tokio::test]
+    async fn test_retry_recovers_from_server_errors() {
+        let mock = MockLlm::start(|_| Reply::content("{}")).await.script([Reply::status(503), Reply::status(500)]);
+        let (config, template) = (mock.config(), news_parser_template());
+        let api = RetryAsk::new(AskFnWrapper { config: &config, template: &template }, 3, StdDuration::from_millis(10));
+
+        assert_eq!(api.ask("article").await.unwrap(), "{}");
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let mock = MockLlm::start(|_| Reply::status(500)).await;
+        let (config, template) = (mock.config(), news_parser_template());
+        let api = RetryAsk::new(AskFnWrapper { config: &config, template: &template }, 1, StdDuration::from_millis(10));
+
+        assert!(api.ask("article").await.is_err());
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
@@ -1,25 +1,119 @@
-use clap::Parser;
+use awful_text_news::editions::check_name;
+use clap::{Parser, Subcommand};
 
 /// Main program to scrape and analyze news articles
 /// from CNN and NPR, outputting JSON/API files and markdown reports.
+///
+/// Without a subcommand, runs every stage in one go.
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Output directory for the JSON API file
-    #[arg(short, long)]
-    pub json_output_dir: String,
+    #[arg(short, long, required = true)]
+    pub json_output_dir: Option<String>,
 
     /// Output directory for the Markdown file
-    #[arg(short, long)]
-    pub markdown_output_dir: String,
+    #[arg(short, long, required = true)]
+    pub markdown_output_dir: Option<String>,
 
     /// Optional path to config.yaml file
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub config: Option<String>,
 
     /// New York Times API key
-    #[arg(long, env = "NYT_API_KEY")]
+    #[arg(long, env = "NYT_API_KEY", global = true)]
     pub nyt_api_key: Option<String>,
+
+    /// Save every index and article response fetched during this run to a directory
+    #[arg(long, value_name = "DIR", conflicts_with = "replay", global = true)]
+    pub record: Option<String>,
+
+    /// Serve all scraper requests from a directory written by --record (no network)
+    #[arg(long, value_name = "DIR", global = true)]
+    pub replay: Option<String>,
+
+    /// Full run and `analyze`: finish the edition an interrupted run left in its journal
+    /// (`<output>.journal` for `analyze`) instead of starting a new one
+    #[arg(long, global = true)]
+    pub resume: bool,
+
+    /// Full run and `analyze`: publish this edition (e.g. `breaking`) instead of the one scheduled for now
+    #[arg(long, value_name = "NAME", value_parser = check_name, global = true)]
+    pub edition: Option<String>,
+
+    /// Full run and `analyze`: when re-running an edition whose articles differ, keep the
+    /// earlier run's articles and add this run's (`analyze` needs `--json-output-dir`)
+    #[arg(long, conflicts_with = "replace", global = true)]
+    pub append: bool,
+
+    /// Full run and `analyze`: when re-running an edition whose articles differ, keep only
+    /// this run's articles (the default)
+    #[arg(long, global = true)]
+    pub replace: bool,
+}
+
+/// Pipeline stages, run one at a time with files in between
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scrape every enabled source and write the raw articles as NDJSON
+    Fetch {
+        /// NDJSON file to write, one article per line
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+    },
+    /// Analyze scraped articles with the LLM and write a FrontPage JSON
+    Analyze {
+        /// NDJSON file written by `fetch`
+        #[arg(short, long, value_name = "FILE")]
+        input: String,
+
+        /// FrontPage JSON file to write
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+
+        /// JSON archive of earlier editions to mark articles new, updated or carried over against,
+        /// and to find an earlier run of this edition in
+        #[arg(short, long)]
+        json_output_dir: Option<String>,
+    },
+    /// Write the edition's Markdown page and JSON API file from a FrontPage JSON
+    Render {
+        /// FrontPage JSON file written by `analyze`
+        #[arg(short, long, value_name = "FILE")]
+        input: String,
+
+        /// Output directory for the JSON API file
+        #[arg(short, long)]
+        json_output_dir: Option<String>,
+
+        /// Output directory for the Markdown file
+        #[arg(short, long)]
+        markdown_output_dir: Option<String>,
+    },
+    /// Add a rendered edition to the mdBook's date TOC, SUMMARY.md and daily_news.md
+    Publish {
+        /// FrontPage JSON file written by `analyze`
+        #[arg(short, long, value_name = "FILE")]
+        input: String,
+
+        /// The mdBook `src` directory the edition was rendered into
+        #[arg(short, long)]
+        markdown_output_dir: String,
+    },
+
+    /// Regenerate every Markdown edition and index from the JSON archive
+    Rebuild {
+        /// The JSON output directory holding `<date>/<edition>.json`
+        #[arg(short, long)]
+        json_output_dir: String,
+
+        /// The mdBook `src` directory to write into
+        #[arg(short, long)]
+        markdown_output_dir: String,
+    },
 }
 
 #[cfg(test)]
@@ -36,8 +130,9 @@ mod tests {
             "./markdown",
         ]);
 
-        assert_eq!(cli.json_output_dir, "./json");
-        assert_eq!(cli.markdown_output_dir, "./markdown");
+        assert_eq!(cli.json_output_dir.as_deref(), Some("./json"));
+        assert_eq!(cli.markdown_output_dir.as_deref(), Some("./markdown"));
+        assert!(cli.command.is_none());
     }
 
     #[test]
@@ -50,7 +145,111 @@ mod tests {
             "/tmp/markdown",
         ]);
 
-        assert_eq!(cli.json_output_dir, "/tmp/json");
-        assert_eq!(cli.markdown_output_dir, "/tmp/markdown");
+        assert_eq!(cli.json_output_dir.as_deref(), Some("/tmp/json"));
+        assert_eq!(cli.markdown_output_dir.as_deref(), Some("/tmp/markdown"));
+    }
+
+    #[test]
+    fn test_record_and_replay_conflict() {
+        let cli = Cli::parse_from(["awful_text_news", "-j", "j", "-m", "m", "--replay", "/tmp/snap"]);
+        assert_eq!(cli.replay.as_deref(), Some("/tmp/snap"));
+        assert!(cli.record.is_none());
+
+        let both = Cli::try_parse_from([
+            "awful_text_news",
+            "-j",
+            "j",
+            "-m",
+            "m",
+            "--record",
+            "/tmp/a",
+            "--replay",
+            "/tmp/b",
+        ]);
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn test_full_run_requires_output_dirs() {
+        assert!(Cli::try_parse_from(["awful_text_news"]).is_err());
+        assert!(Cli::try_parse_from(["awful_text_news", "-j", "j"]).is_err());
+    }
+
+    #[test]
+    fn test_fetch_subcommand() {
+        let cli = Cli::parse_from(["awful_text_news", "fetch", "-o", "articles.ndjson", "--replay", "/tmp/snap"]);
+        assert!(matches!(cli.command, Some(Command::Fetch { ref output }) if output == "articles.ndjson"));
+        assert_eq!(cli.replay.as_deref(), Some("/tmp/snap"));
+    }
+
+    #[test]
+    fn test_analyze_subcommand() {
+        let cli = Cli::parse_from(["awful_text_news", "-c", "conf.yaml", "analyze", "-i", "a.ndjson", "-o", "fp.json"]);
+        assert!(matches!(cli.command, Some(Command::Analyze { ref input, .. }) if input == "a.ndjson"));
+        assert_eq!(cli.config.as_deref(), Some("conf.yaml"));
+        assert!(!cli.resume);
+    }
+
+    #[test]
+    fn test_resume() {
+        let cli = Cli::parse_from(["awful_text_news", "analyze", "-i", "a.ndjson", "-o", "fp.json", "--resume", "-j", "json"]);
+        assert!(matches!(cli.command, Some(Command::Analyze { json_output_dir: Some(ref dir), .. }) if dir == "json"));
+        assert!(cli.resume);
+
+        // Before or after the subcommand, it is the same flag
+        let cli = Cli::parse_from(["awful_text_news", "--resume", "analyze", "-i", "a.ndjson", "-o", "fp.json"]);
+        assert!(cli.resume);
+
+        let cli = Cli::parse_from(["awful_text_news", "-j", "j", "-m", "m", "--resume"]);
+        assert!(cli.resume);
+    }
+
+    #[test]
+    fn test_render_subcommand() {
+        let cli = Cli::parse_from(["awful_text_news", "render", "-i", "fp.json", "-m", "book/src"]);
+        match cli.command {
+            Some(Command::Render { input, json_output_dir, markdown_output_dir }) => {
+                assert_eq!(input, "fp.json");
+                assert!(json_output_dir.is_none());
+                assert_eq!(markdown_output_dir.as_deref(), Some("book/src"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_publish_requires_markdown_dir() {
+        assert!(Cli::try_parse_from(["awful_text_news", "publish", "-i", "fp.json"]).is_err());
+    }
+
+    #[test]
+    fn test_append_and_replace() {
+        let analyze = Cli::try_parse_from(["awful_text_news", "--append", "analyze", "-i", "a.ndjson", "-o", "fp.json", "-j", "api"]).unwrap();
+        assert!(analyze.append && !analyze.replace);
+        assert!(Cli::try_parse_from(["awful_text_news", "analyze", "-i", "a", "-o", "b", "--append", "--replace"]).is_err());
+        assert!(Cli::try_parse_from(["awful_text_news", "-j", "api", "-m", "md", "--append", "--replace"]).is_err());
+        assert!(Cli::try_parse_from(["awful_text_news", "-j", "api", "-m", "md", "--append"]).unwrap().append);
+    }
+
+    #[test]
+    fn test_edition() {
+        let extra = Cli::try_parse_from(["awful_text_news", "-j", "api", "-m", "md", "--edition", "breaking"]).unwrap();
+        assert_eq!(extra.edition.as_deref(), Some("breaking"));
+        assert!(Cli::try_parse_from(["awful_text_news", "-j", "api", "-m", "md", "--edition", "../x"]).is_err());
+
+        let analyze = Cli::try_parse_from(["awful_text_news", "analyze", "-i", "a", "-o", "b", "--edition", "late"]).unwrap();
+        assert_eq!(analyze.edition.as_deref(), Some("late"));
+    }
+
+    #[test]
+    fn test_rebuild_subcommand() {
+        let rebuild = Cli::try_parse_from(["awful_text_news", "rebuild", "-j", "api", "-m", "book/src"]).unwrap();
+        match rebuild.command {
+            Some(Command::Rebuild { json_output_dir, markdown_output_dir }) => {
+                assert_eq!(json_output_dir, "api");
+                assert_eq!(markdown_output_dir, "book/src");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
     }
 }
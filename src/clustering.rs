@@ -0,0 +1,293 @@
+use crate::models::{AwfulNewsArticle, Story};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info, instrument};
+
+/// Score at which two articles are taken to report the same story
+pub const DEFAULT_THRESHOLD: f64 = 0.45;
+
+/// Weights of the text, named-entity and tag similarity in the combined score
+const TEXT_WEIGHT: f64 = 0.5;
+const ENTITY_WEIGHT: f64 = 0.3;
+const TAG_WEIGHT: f64 = 0.2;
+
+/// Shared entities needed for full entity similarity, so one shared "United States" is not enough
+const MIN_ENTITY_OVERLAP: usize = 3;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "were", "has", "have", "had", "that", "this", "with", "from", "its",
+    "but", "not", "his", "her", "their", "they", "will", "would", "said", "says", "after", "over", "into",
+    "about", "than", "been", "who", "which", "while", "also", "more", "new", "article",
+];
+
+/// What an article is compared on
+struct Features {
+    terms: HashMap<String, f64>,
+    norm: f64,
+    entities: HashSet<String>,
+    tags: HashSet<String>,
+}
+
+impl Features {
+    fn new(article: &AwfulNewsArticle) -> Self {
+        let mut terms: HashMap<String, f64> = HashMap::new();
+        // Headlines count double: they name the event, summaries add background
+        for (text, weight) in [(&article.title, 2.0), (&article.summaryOfNewsArticle, 1.0)] {
+            for word in words(text) {
+                *terms.entry(word).or_default() += weight;
+            }
+        }
+        let norm = terms.values().map(|v| v * v).sum::<f64>().sqrt();
+        let entities = article.namedEntities.iter().map(|e| normalize_name(&e.name)).filter(|e| !e.is_empty()).collect();
+        let tags = article.tags.iter().map(|t| normalize_name(t)).filter(|t| !t.is_empty()).collect();
+        Self {
+            terms,
+            norm,
+            entities,
+            tags,
+        }
+    }
+}
+
+/// Lowercase content words of at least three characters
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+}
+
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_prefix("the ").unwrap_or(&name).to_string()
+}
+
+fn cosine(a: &Features, b: &Features) -> f64 {
+    if a.norm == 0.0 || b.norm == 0.0 {
+        return 0.0;
+    }
+    let dot: f64 = a.terms.iter().filter_map(|(t, x)| b.terms.get(t).map(|y| x * y)).sum();
+    dot / (a.norm * b.norm)
+}
+
+/// Shared entities over the smaller list, counting short lists as `MIN_ENTITY_OVERLAP` long
+fn entity_overlap(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let shared = a.intersection(b).count();
+    if shared == 0 {
+        return 0.0;
+    }
+    shared as f64 / a.len().min(b.len()).max(MIN_ENTITY_OVERLAP) as f64
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Combined similarity in `0.0..=1.0`
+fn similarity(a: &Features, b: &Features) -> f64 {
+    TEXT_WEIGHT * cosine(a, b) + ENTITY_WEIGHT * entity_overlap(&a.entities, &b.entities) + TAG_WEIGHT * jaccard(&a.tags, &b.tags)
+}
+
+/// Group articles that report the same event into stories.
+///
+/// Pairs are merged most-similar first, and two groups are only joined when their
+/// average pairwise score clears `threshold`, so one loose match cannot chain unrelated
+/// stories together. Stories covered by the most outlets come first; every article is
+/// in exactly one story.
+#[instrument(level = "info", skip_all, fields(articles = articles.len()))]
+pub fn cluster(articles: &[AwfulNewsArticle], threshold: f64) -> Vec<Story> {
+    let n = articles.len();
+    let features: Vec<Features> = articles.iter().map(Features::new).collect();
+    let mut scores = vec![0.0; n * n];
+    let mut pairs = Vec::new();
+    for i in 0..n {
+        for j in i + 1..n {
+            let score = similarity(&features[i], &features[j]);
+            scores[i * n + j] = score;
+            scores[j * n + i] = score;
+            if score >= threshold {
+                pairs.push((score, i, j));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut group_of: Vec<usize> = (0..n).collect();
+    let mut groups: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    for (score, i, j) in pairs {
+        let (a, b) = (group_of[i], group_of[j]);
+        if a == b {
+            continue;
+        }
+        let linkage = groups[a].iter().cartesian_product(&groups[b]).map(|(x, y)| scores[x * n + y]).sum::<f64>()
+            / (groups[a].len() * groups[b].len()) as f64;
+        if linkage < threshold {
+            debug!(i, j, score, linkage, "Not merging: groups too dissimilar on average");
+            continue;
+        }
+        let moved = std::mem::take(&mut groups[b]);
+        for &m in &moved {
+            group_of[m] = a;
+        }
+        groups[a].extend(moved);
+    }
+
+    let mut stories: Vec<Story> = groups
+        .into_iter()
+        .filter(|g| !g.is_empty())
+        .map(|mut members| {
+            members.sort_unstable();
+            story(articles, members)
+        })
+        .collect();
+    stories.sort_by_key(|s| (std::cmp::Reverse(s.outlets.len()), s.articles.iter().copied().min()));
+
+    info!(stories = stories.len(), multi_source = stories.iter().filter(|s| s.articles.len() > 1).count(), "Clustered articles into stories");
+    stories
+}
+
+/// A story for `members`, led by the article with the fullest summary
+fn story(articles: &[AwfulNewsArticle], mut members: Vec<usize>) -> Story {
+    let lead_pos = members
+        .iter()
+        .enumerate()
+        .max_by_key(|&(pos, &m)| (articles[m].summaryOfNewsArticle.len(), std::cmp::Reverse(pos)))
+        .map(|(pos, _)| pos)
+        .unwrap_or(0);
+    let lead = members.remove(lead_pos);
+    members.insert(0, lead);
+
+    let counts = members.iter().map(|&m| articles[m].category.as_str()).counts();
+    let top = counts.values().copied().max().unwrap_or(0);
+    let category = if counts.get(articles[lead].category.as_str()) == Some(&top) {
+        articles[lead].category.clone()
+    } else {
+        members
+            .iter()
+            .map(|&m| &articles[m].category)
+            .find(|c| counts[c.as_str()] == top)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    Story {
+        title: articles[lead].title.clone(),
+        category,
+        outlets: members.iter().filter_map(|&m| articles[m].source_tag()).unique().collect(),
+        articles: members,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NamedEntity;
+
+    fn article(source: &str, title: &str, summary: &str, entities: &[&str], tags: &[&str]) -> AwfulNewsArticle {
+        AwfulNewsArticle {
+            source: Some(source.to_string()),
+            dateOfPublication: "2025-05-06".to_string(),
+            timeOfPublication: "14:30:00".to_string(),
+            title: title.to_string(),
+            category: "Conflict & War".to_string(),
+            summaryOfNewsArticle: summary.to_string(),
+            keyTakeAways: vec![],
+            namedEntities: entities
+                .iter()
+                .map(|name| NamedEntity {
+                    name: name.to_string(),
+                    whatIsThisEntity: String::new(),
+                    whyIsThisEntityRelevantToTheArticle: String::new(),
+                })
+                .collect(),
+            importantDates: vec![],
+            importantTimeframes: vec![],
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            content: None,
+            publishedAt: None,
+            novelty: None,
+        }
+    }
+
+    fn edition() -> Vec<AwfulNewsArticle> {
+        vec![
+            article(
+                "https://lite.cnn.com/2025/05/06/asia/ship",
+                "Former US warship sinks before Philippine drills",
+                "The ex-USS Brattleboro sank before US and Philippine forces could use it as a target in joint drills near Scarborough Shoal.",
+                &["USS Brattleboro", "Philippines", "United States", "Scarborough Shoal"],
+                &["philippines", "military", "navy"],
+            ),
+            article(
+                "https://www.bbc.com/news/articles/abc",
+                "Old US warship used as drill target sinks early",
+                "A World War II-era US ship meant as the target of US-Philippine drills near Scarborough Shoal sank before the exercise.",
+                &["Brattleboro", "Philippines", "the United States", "Scarborough Shoal", "MARSTRIKE"],
+                &["military", "philippines"],
+            ),
+            article(
+                "https://apnews.com/article/rates",
+                "Federal Reserve holds interest rates steady",
+                "The Federal Reserve kept its benchmark interest rate unchanged, citing uncertainty over tariffs and inflation in the United States.",
+                &["Federal Reserve", "United States", "Jerome Powell"],
+                &["economy", "interest rates"],
+            ),
+            article(
+                "https://www.aljazeera.com/news/2025/5/6/ship",
+                "Philippines says sinking of US warship target poses no danger",
+                "The Philippine military said the sinking of the ex-USS Brattleboro before joint drills with US forces posed no environmental danger.",
+                &["USS Brattleboro", "Philippines", "Scarborough Shoal"],
+                &["philippines", "navy", "environment"],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_same_event_from_several_outlets_is_one_story() {
+        let stories = cluster(&edition(), DEFAULT_THRESHOLD);
+        assert_eq!(stories.len(), 2);
+
+        let ship = &stories[0];
+        let mut members = ship.articles.clone();
+        members.sort();
+        assert_eq!(members, vec![0, 1, 3]);
+        assert_eq!(ship.outlets.len(), 3);
+        for outlet in ["cnn", "bbc", "aljazeera"] {
+            assert!(ship.outlets.iter().any(|o| o == outlet), "missing {}", outlet);
+        }
+        assert_eq!(ship.category, "Conflict & War");
+
+        assert_eq!(stories[1].articles, vec![2]);
+        assert_eq!(stories[1].title, "Federal Reserve holds interest rates steady");
+        assert_eq!(stories[1].outlets, vec!["apnews"]);
+    }
+
+    #[test]
+    fn test_lead_has_fullest_summary() {
+        let stories = cluster(&edition(), DEFAULT_THRESHOLD);
+        // Al Jazeera's summary is the longest of the three
+        assert_eq!(stories[0].articles[0], 3);
+        assert_eq!(stories[0].title, "Philippines says sinking of US warship target poses no danger");
+    }
+
+    #[test]
+    fn test_one_shared_entity_is_not_enough() {
+        let articles = edition();
+        let a = Features::new(&articles[0]);
+        let fed = Features::new(&articles[2]);
+        assert!(similarity(&a, &fed) < DEFAULT_THRESHOLD);
+        assert!(similarity(&a, &Features::new(&articles[1])) >= DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn test_threshold_above_one_keeps_every_article_apart() {
+        let stories = cluster(&edition(), 1.1);
+        assert_eq!(stories.len(), 4);
+        assert!(stories.iter().all(|s| s.articles.len() == 1));
+        assert!(cluster(&[], DEFAULT_THRESHOLD).is_empty());
+    }
+}
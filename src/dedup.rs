@@ -0,0 +1,203 @@
+use crate::analyzer::chunking::HEADER_PREFIXES;
+use crate::models::NewsArticle;
+use crate::utils::stable_hash;
+use std::collections::HashMap;
+use tracing::{info, instrument};
+use url::Url;
+
+/// Query parameters that only track where a click came from
+const TRACKING_PARAMS: &[&str] = &[
+    "gclid", "fbclid", "dclid", "msclkid", "igshid", "mc_cid", "mc_eid", "ocid", "cmpid", "smid", "smtyp",
+    "ref", "ref_src", "referrer", "taid", "at_medium", "at_campaign", "at_link", "partner", "_ga",
+];
+
+/// SimHash fingerprints at most this many bits apart are the same text
+const MAX_DISTANCE: u32 = 3;
+
+/// Bodies shorter than this many words are too short to fingerprint reliably
+const MIN_WORDS: usize = 40;
+
+/// Words per shingle hashed into the fingerprint
+const SHINGLE: usize = 3;
+
+/// A key that is equal for URLs pointing at the same page: `https`, no `www.`/`m.`
+/// host prefix, no fragment, no tracking parameters, remaining parameters sorted and
+/// no trailing slash. Unparseable URLs are compared as given.
+pub fn canonical_url(raw: &str) -> String {
+    let Ok(mut url) = Url::parse(raw.trim()) else {
+        return raw.trim().to_string();
+    };
+    url.set_fragment(None);
+    if url.scheme() == "http" {
+        let _ = url.set_scheme("https");
+    }
+    if let Some(host) = url.host_str() {
+        let bare = host.strip_prefix("www.").or_else(|| host.strip_prefix("m.")).unwrap_or(host).to_string();
+        let _ = url.set_host(Some(&bare));
+    }
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| {
+            let k = k.to_ascii_lowercase();
+            !k.starts_with("utm_") && !TRACKING_PARAMS.contains(&k.as_str())
+        })
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    params.sort();
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(if path.is_empty() { "/" } else { &path });
+    url.to_string()
+}
+
+/// 64-bit SimHash over word shingles of the article body (header lines left out);
+/// `None` if the body is too short to say anything
+pub fn simhash(content: &str) -> Option<u64> {
+    let words: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !HEADER_PREFIXES.iter().any(|h| l.starts_with(h)))
+        .flat_map(|l| l.split(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(SHINGLE) {
+        let hash = stable_hash(shingle.join(" ").as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if (hash >> bit) & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    Some(weights.iter().enumerate().filter(|(_, w)| **w > 0).fold(0, |acc, (bit, _)| acc | 1 << bit))
+}
+
+/// Whether two SimHash fingerprints are close enough to be the same text
+pub fn near(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= MAX_DISTANCE
+}
+
+/// Drop articles that are the same page under another URL, or the same text under
+/// another page. The first copy keeps its place; if a later copy has more text, its
+/// content replaces the first one's, and its publication time fills in a missing one.
+#[instrument(level = "info", skip_all, fields(articles = articles.len()))]
+pub fn dedupe(articles: Vec<NewsArticle>) -> Vec<NewsArticle> {
+    let total = articles.len();
+    let mut kept: Vec<NewsArticle> = Vec::with_capacity(total);
+    let mut fingerprints: Vec<Option<u64>> = Vec::with_capacity(total);
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+
+    for article in articles {
+        let key = canonical_url(&article.source);
+        let fingerprint = simhash(&article.content);
+        let duplicate_of = by_url.get(&key).copied().or_else(|| {
+            let fingerprint = fingerprint?;
+            fingerprints
+                .iter()
+                .position(|f| f.is_some_and(|f| near(f, fingerprint)))
+        });
+
+        match duplicate_of {
+            Some(i) => {
+                info!(kept = %kept[i].source, dropped = %article.source, "Skipping duplicate article");
+                if article.content.len() > kept[i].content.len() {
+                    kept[i].content = article.content;
+                    fingerprints[i] = fingerprint;
+                }
+                kept[i].published_at = kept[i].published_at.or(article.published_at);
+                by_url.entry(key).or_insert(i);
+            }
+            None => {
+                by_url.insert(key, kept.len());
+                fingerprints.push(fingerprint);
+                kept.push(article);
+            }
+        }
+    }
+
+    info!(total, unique = kept.len(), duplicates = total - kept.len(), "De-duplicated articles");
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article(source: &str, content: &str) -> NewsArticle {
+        NewsArticle {
+            source: source.to_string(),
+            content: content.to_string(),
+            published_at: None,
+        }
+    }
+
+    const BODY: &str = "The ex-USS Brattleboro, a World War II-era ship, sank before US and Philippine \
+        forces could use it as the main target of joint drills near Scarborough Shoal. The vessel \
+        served in the Battle of Leyte and the invasion of Okinawa. The Philippine military said the \
+        sinking posed no environmental danger because the ship had been cleaned before it was towed \
+        out, and that the exercise would still meet its training objectives with other targets.";
+
+    #[test]
+    fn test_canonical_url() {
+        assert_eq!(
+            canonical_url("http://www.nytimes.com/2025/05/06/world/ship.html?smid=url-share&utm_source=x#comments"),
+            "https://nytimes.com/2025/05/06/world/ship.html"
+        );
+        assert_eq!(
+            canonical_url("https://apnews.com/article/abc/?b=2&utm_medium=social&a=1"),
+            canonical_url("https://APNEWS.com/article/abc?a=1&b=2")
+        );
+        assert_eq!(canonical_url("https://m.bbc.com/news/x"), "https://bbc.com/news/x");
+        assert_eq!(canonical_url("https://lite.cnn.com/"), "https://lite.cnn.com/");
+        assert_ne!(canonical_url("https://example.com/a?id=1"), canonical_url("https://example.com/a?id=2"));
+        assert_eq!(canonical_url(" not a url "), "not a url");
+    }
+
+    #[test]
+    fn test_simhash_tolerates_small_edits() {
+        let a = simhash(&format!("Title: Warship sinks\n\n{}", BODY)).unwrap();
+        let b = simhash(&format!("Title: Old warship sinks early\n\n{} Advert", BODY)).unwrap();
+        assert!((a ^ b).count_ones() <= MAX_DISTANCE, "distance {}", (a ^ b).count_ones());
+
+        let other = "The Federal Reserve kept its benchmark interest rate unchanged on Wednesday, saying that \
+            uncertainty over tariffs had grown and that the risks of both higher unemployment and higher \
+            inflation had risen. Officials gave no signal of when rates might be cut and markets were mixed \
+            after the decision was announced in Washington by the chair.";
+        let c = simhash(other).unwrap();
+        assert!((a ^ c).count_ones() > MAX_DISTANCE);
+        assert!(simhash("Title: Short\n\nToo short to fingerprint.").is_none());
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let dated = NewsArticle {
+            published_at: chrono::DateTime::parse_from_rfc3339("2025-05-06T09:15:00-04:00").ok(),
+            ..article("https://www.apnews.com/article/rates", "Title: Rates\n\nShort body, longer copy.")
+        };
+        let articles = vec![
+            article("https://apnews.com/article/ship", &format!("Title: Warship sinks\n\n{}", BODY)),
+            article("https://apnews.com/article/rates?utm_source=google", "Title: Rates\n\nShort body."),
+            dated,
+            article("https://lite.cnn.com/2025/05/06/ship", &format!("Title: Warship sinks\n\n{}", BODY)),
+            article("https://text.npr.org/nx-1", "Title: Short\n\nShort body."),
+        ];
+        let unique = dedupe(articles);
+        let sources: Vec<&str> = unique.iter().map(|a| a.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec!["https://apnews.com/article/ship", "https://apnews.com/article/rates?utm_source=google", "https://text.npr.org/nx-1"]
+        );
+        // The longer copy's text is kept, short identical bodies are not fingerprinted
+        assert_eq!(unique[1].content, "Title: Rates\n\nShort body, longer copy.");
+        assert_eq!(unique[1].published_at.unwrap().to_rfc3339(), "2025-05-06T09:15:00-04:00");
+    }
+}
@@ -0,0 +1,270 @@
+use crate::models::{EditionStatus, FrontPage};
+use crate::settings::{EditionSettings, EditionsSettings};
+use crate::utils::upcase;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use std::error::Error;
+use tracing::{debug, info, warn};
+
+/// One edition from the schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edition {
+    /// Used in file names, e.g. `2025-05-06_<name>.md`
+    pub name: String,
+    /// Shown in the indexes
+    pub label: String,
+    /// Local time the edition's window opens; `None` for editions only run with `--edition`
+    starts: Option<NaiveTime>,
+    /// Weekdays the window opens on; every day when empty
+    days: Vec<Weekday>,
+}
+
+impl Edition {
+    fn new(name: &str, label: Option<&str>, starts: Option<NaiveTime>) -> Self {
+        Self {
+            name: name.to_string(),
+            label: label.map(str::to_string).unwrap_or_else(|| upcase(name)),
+            starts,
+            days: Vec::new(),
+        }
+    }
+
+    /// When this edition's window last opened at or before `now`, looking back a week
+    fn last_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let starts = self.starts?;
+        (0..=7)
+            .map(|days_back| now.date() - Duration::days(days_back))
+            .filter(|date| self.days.is_empty() || self.days.contains(&date.weekday()))
+            .map(|date| date.and_time(starts))
+            .find(|start| *start <= now)
+    }
+}
+
+/// The editions published each day and the clock they follow. Each scheduled edition
+/// runs from its start time until the next one starts.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// `None` follows the host's local time
+    time_zone: Option<Tz>,
+    editions: Vec<Edition>,
+}
+
+impl Default for Schedule {
+    /// Morning from midnight, afternoon from 08:00 and evening from 16:00, host time
+    fn default() -> Self {
+        let at = |h| NaiveTime::from_hms_opt(h, 0, 0);
+        Self {
+            time_zone: None,
+            editions: vec![
+                Edition::new("morning", None, at(0)),
+                Edition::new("afternoon", None, at(8)),
+                Edition::new("evening", None, at(16)),
+            ],
+        }
+    }
+}
+
+impl Schedule {
+    /// Check and load the configured schedule; an empty list keeps the default editions
+    pub fn from_settings(settings: &EditionsSettings) -> Result<Self, Box<dyn Error>> {
+        let time_zone = settings
+            .time_zone
+            .as_deref()
+            .map(|name| name.parse::<Tz>().map_err(|e| format!("time_zone {:?}: {}", name, e)))
+            .transpose()?;
+        if time_zone.is_none() {
+            warn!("No editions.time_zone configured; edition dates follow the host's time zone");
+        }
+        let mut editions = Vec::with_capacity(settings.schedule.len());
+        for EditionSettings { name, label, starts, days } in &settings.schedule {
+            check_name(name)?;
+            if editions.iter().any(|e: &Edition| e.name == *name) {
+                return Err(format!("edition {} is defined twice", name).into());
+            }
+            let starts = starts
+                .as_deref()
+                .map(|s| NaiveTime::parse_from_str(s, "%H:%M").map_err(|e| format!("edition {}: starts {:?}: {}", name, s, e)))
+                .transpose()?;
+            let days = days
+                .iter()
+                .map(|d| d.parse::<Weekday>().map_err(|_| format!("edition {}: {:?} is not a weekday", name, d)))
+                .collect::<Result<Vec<_>, _>>()?;
+            editions.push(Edition {
+                days,
+                ..Edition::new(name, label.as_deref(), starts)
+            });
+        }
+
+        let schedule = if editions.is_empty() {
+            Self {
+                time_zone,
+                ..Self::default()
+            }
+        } else if editions.iter().all(|e| e.starts.is_none()) {
+            return Err("the edition schedule needs at least one edition with a `starts` time".into());
+        } else {
+            Self { time_zone, editions }
+        };
+        info!(
+            time_zone = schedule.time_zone.map_or("local", |tz| tz.name()),
+            editions = schedule.editions.len(),
+            "Loaded edition schedule"
+        );
+        Ok(schedule)
+    }
+
+    /// The current time on the schedule's clock
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        match &self.time_zone {
+            Some(tz) => Utc::now().with_timezone(tz).fixed_offset(),
+            None => Local::now().fixed_offset(),
+        }
+    }
+
+    /// The scheduled edition whose window contains `now`: the one that started most
+    /// recently, the first listed on a tie
+    pub fn edition_at(&self, now: NaiveDateTime) -> &Edition {
+        let mut current: Option<(&Edition, NaiveDateTime)> = None;
+        for edition in &self.editions {
+            if let Some(start) = edition.last_start(now)
+                && current.is_none_or(|(_, latest)| start > latest)
+            {
+                current = Some((edition, start));
+            }
+        }
+        // Every window reopens within a week, so one has always started
+        let edition = current.map_or(&self.editions[0], |(edition, _)| edition);
+        debug!(%now, edition = %edition.name, "Picked edition from schedule");
+        edition
+    }
+
+    /// The edition called `name`; a name not in the schedule is an extra edition labelled after it
+    pub fn named(&self, name: &str) -> Edition {
+        self.editions
+            .iter()
+            .find(|e| e.name == name)
+            .cloned()
+            .unwrap_or_else(|| Edition::new(name, None, None))
+    }
+
+    /// An empty edition stamped with the current time: `edition` if given, otherwise
+    /// the one scheduled now
+    pub fn front_page(&self, edition: Option<&str>) -> FrontPage {
+        self.front_page_at(self.now(), edition)
+    }
+
+    /// An empty edition stamped with `now`.
+    ///
+    /// Every output files an edition under the date its window last opened, so a run
+    /// after midnight in a window that opened the evening before belongs to that day.
+    /// Editions without a window take the current date.
+    pub fn front_page_at(&self, now: DateTime<FixedOffset>, edition: Option<&str>) -> FrontPage {
+        let edition = match edition {
+            Some(name) => self.named(name),
+            None => self.edition_at(now.naive_local()).clone(),
+        };
+        let date = edition.last_start(now.naive_local()).map_or(now.date_naive(), |start| start.date());
+        FrontPage {
+            local_date: date.to_string(),
+            time_of_day: edition.name,
+            edition_label: Some(edition.label),
+            local_time: now.time().to_string(),
+            // To the second, as RFC 3339 stamps are usually written
+            published_at: now.with_nanosecond(0),
+            status: EditionStatus::default(),
+            articles: Vec::new(),
+            stories: Vec::new(),
+        }
+    }
+}
+
+/// Edition names end up in file names, so only letters, digits, `-` and `_` are allowed
+pub fn check_name(name: &str) -> Result<String, String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(name.to_string())
+    } else {
+        Err(format!("edition name {:?} may only use letters, digits, `-` and `_`", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_default_schedule() {
+        let schedule = Schedule::default();
+        assert_eq!(schedule.edition_at(at("2025-05-06 06:30")).name, "morning");
+        assert_eq!(schedule.edition_at(at("2025-05-06 08:00")).name, "afternoon");
+        assert_eq!(schedule.edition_at(at("2025-05-06 12:00")).name, "afternoon");
+        assert_eq!(schedule.edition_at(at("2025-05-06 20:00")).name, "evening");
+        assert_eq!(schedule.edition_at(at("2025-05-06 20:00")).label, "Evening");
+    }
+
+    #[test]
+    fn test_configured_schedule() {
+        let settings: EditionsSettings = serde_yaml::from_str(
+            r#"
+schedule:
+  - name: early
+    label: Early Bird
+    starts: "05:00"
+  - name: late
+    starts: "17:30"
+  - name: weekend
+    starts: "09:00"
+    days: [sat, sun]
+  - name: breaking
+"#,
+        )
+        .unwrap();
+        let schedule = Schedule::from_settings(&settings).unwrap();
+
+        // 2025-05-06 is a Tuesday; before 05:00 the previous day's late edition is still on
+        assert_eq!(schedule.edition_at(at("2025-05-06 04:59")).name, "late");
+        assert_eq!(schedule.edition_at(at("2025-05-06 05:00")).label, "Early Bird");
+        assert_eq!(schedule.edition_at(at("2025-05-06 18:00")).name, "late");
+        assert_eq!(schedule.edition_at(at("2025-05-10 10:00")).name, "weekend");
+        assert_eq!(schedule.edition_at(at("2025-05-12 06:00")).name, "early");
+
+        assert_eq!(schedule.named("breaking").label, "Breaking");
+        assert_eq!(schedule.named("special").label, "Special");
+        let front_page = schedule.front_page(Some("breaking"));
+        assert_eq!(front_page.time_of_day, "breaking");
+        assert_eq!(front_page.label(), "Breaking");
+    }
+
+    #[test]
+    fn test_edition_after_midnight_keeps_its_date() {
+        let settings: EditionsSettings =
+            serde_yaml::from_str("schedule: [{name: early, starts: '05:00'}, {name: late, starts: '17:30'}, {name: extra}]").unwrap();
+        let schedule = Schedule::from_settings(&settings).unwrap();
+        let now = DateTime::parse_from_rfc3339("2025-05-07T01:15:00-04:00").unwrap();
+
+        let late = schedule.front_page_at(now, None);
+        assert_eq!((late.local_date.as_str(), late.time_of_day.as_str()), ("2025-05-06", "late"));
+        assert_eq!(late.local_time, "01:15:00");
+        assert_eq!(late.published_at, Some(now));
+        assert!(serde_json::to_string(&late).unwrap().contains(r#""published_at":"2025-05-07T01:15:00-04:00""#));
+        assert_eq!(schedule.front_page_at(now, Some("late")).local_date, "2025-05-06");
+        assert_eq!(schedule.front_page_at(now, Some("early")).local_date, "2025-05-06");
+        assert_eq!(schedule.front_page_at(now, Some("extra")).local_date, "2025-05-07");
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let parse = |yaml: &str| Schedule::from_settings(&serde_yaml::from_str(yaml).unwrap());
+        assert!(parse("schedule: [{name: ../x, starts: '01:00'}]").is_err());
+        assert!(parse("schedule: [{name: a, starts: '25:00'}]").is_err());
+        assert!(parse("schedule: [{name: a, starts: '01:00', days: [someday]}]").is_err());
+        assert!(parse("schedule: [{name: a, starts: '01:00'}, {name: a, starts: '02:00'}]").is_err());
+        assert!(parse("schedule: [{name: extra}]").is_err());
+        assert!(parse("time_zone: Not/A_Zone").is_err());
+        assert!(parse("time_zone: America/New_York").is_ok());
+        assert!(check_name("breaking-news_2").is_ok());
+    }
+}
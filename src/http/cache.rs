@@ -0,0 +1,104 @@
+use crate::utils::stable_hash;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{debug, warn};
+
+/// On-disk copy of a successful response plus the validators needed to revalidate it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// URL as requested (the cache key)
+    pub url: String,
+    /// URL after redirects
+    pub final_url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// RFC 3339 time of the last full download
+    pub fetched_at: String,
+    pub body: String,
+}
+
+/// Directory of `<fnv1a(url)>.json` files
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path_for(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", stable_hash(url.as_bytes())))
+    }
+
+    /// The cached entry for `url`, if any; unreadable entries count as misses
+    pub async fn load(&self, url: &str) -> Option<CachedResponse> {
+        let path = self.path_for(url);
+        let bytes = fs::read(&path).await.ok()?;
+        match serde_json::from_slice::<CachedResponse>(&bytes) {
+            // Guard against the (unlikely) hash collision
+            Ok(entry) if entry.url == url => Some(entry),
+            Ok(_) => None,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt cache entry");
+                None
+            }
+        }
+    }
+
+    /// Write an entry via a temp file + rename so readers never see half a file
+    pub async fn store(&self, entry: &CachedResponse) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.path_for(&entry.url);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(entry)?).await?;
+        fs::rename(&tmp, &path).await?;
+        debug!(url = %entry.url, path = %path.display(), "Cached response");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str) -> CachedResponse {
+        CachedResponse {
+            url: url.to_string(),
+            final_url: format!("{}/", url),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            fetched_at: "2025-05-06T14:30:00+00:00".to_string(),
+            body: "<html>cached</html>".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("atn_http_cache_{}", std::process::id()));
+        let cache = HttpCache::new(&dir);
+        let url = "https://text.npr.org";
+
+        assert!(cache.load(url).await.is_none());
+        cache.store(&entry(url)).await.unwrap();
+        assert_eq!(cache.load(url).await, Some(entry(url)));
+        assert!(cache.load("https://lite.cnn.com").await.is_none());
+
+        // A corrupt entry is a miss, not an error
+        std::fs::write(cache.path_for(url), b"{not json").unwrap();
+        assert!(cache.load(url).await.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_is_stable_per_url() {
+        let cache = HttpCache::new("/tmp/cache");
+        assert_eq!(cache.path_for("https://a.example/x"), cache.path_for("https://a.example/x"));
+        assert_ne!(cache.path_for("https://a.example/x"), cache.path_for("https://a.example/y"));
+        assert!(cache.path_for("https://a.example/x").starts_with("/tmp/cache"));
+    }
+}
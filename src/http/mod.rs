@@ -0,0 +1,569 @@
+pub mod cache;
+pub mod robots;
+
+use crate::settings::{HttpSettings, SourceSettings};
+use cache::{CachedResponse, HttpCache};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use rand::{rng, Rng};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+use reqwest::{Client, StatusCode, Url};
+use robots::RobotRules;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+use tracing::{debug, instrument, warn};
+
+/// Honest default User-Agent; override with `http.user_agent` in config.yaml
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "awful_text_news/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/graves/awful_text_news)"
+);
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+/// Longest `Retry-After` we are willing to sleep for
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+static FETCHER: OnceCell<Fetcher> = OnceCell::new();
+
+/// Query parameters whose values are kept out of snapshots
+const SECRET_PARAMS: &[&str] = &["api-key", "api_key", "apikey"];
+
+/// Configure the shared fetcher; must run before the first request to take effect
+pub fn init(settings: HttpSettings, mode: Mode) {
+    if FETCHER.set(Fetcher::new(settings, mode)).is_err() {
+        warn!("HTTP fetcher already initialized; ignoring new settings");
+    }
+}
+
+/// The fetcher every source goes through (built with defaults if `init` was never called)
+pub fn fetcher() -> &'static Fetcher {
+    FETCHER.get_or_init(|| Fetcher::new(HttpSettings::default(), Mode::Live))
+}
+
+/// Where responses come from
+#[derive(Debug, Clone)]
+pub enum Mode {
+    /// Fetch from the network
+    Live,
+    /// Fetch from the network and save every successful response to a snapshot directory
+    Record(PathBuf),
+    /// Serve every request from a snapshot directory; nothing touches the network
+    Replay(PathBuf),
+}
+
+/// Per-source knobs for a request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchOptions {
+    /// Overrides `http.respect_robots` when set
+    pub respect_robots: Option<bool>,
+}
+
+impl From<&SourceSettings> for FetchOptions {
+    fn from(settings: &SourceSettings) -> Self {
+        Self {
+            respect_robots: settings.respect_robots,
+        }
+    }
+}
+
+/// A successfully fetched page
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// Final URL after redirects
+    pub url: Url,
+    pub body: String,
+}
+
+/// Shared HTTP client with per-host concurrency / rate limits and retries
+pub struct Fetcher {
+    client: Client,
+    settings: HttpSettings,
+    cache: Option<HttpCache>,
+    /// `--record` / `--replay` directory (same layout as the HTTP cache)
+    snapshot: Option<HttpCache>,
+    replay: bool,
+    hosts: Mutex<HashMap<String, Arc<HostLimiter>>>,
+}
+
+struct Response {
+    url: Url,
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+struct HostLimiter {
+    permits: Semaphore,
+    next_slot: Mutex<Instant>,
+    robots: tokio::sync::OnceCell<RobotRules>,
+}
+
+impl Fetcher {
+    pub fn new(settings: HttpSettings, mode: Mode) -> Self {
+        let client = Client::builder()
+            .user_agent(settings.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .build()
+            .expect("failed to build reqwest client");
+
+        let cache = match (&settings.cache_dir, settings.cache) {
+            (Some(dir), true) => Some(HttpCache::new(dir)),
+            _ => None,
+        };
+
+        let (snapshot, replay) = match mode {
+            Mode::Live => (None, false),
+            Mode::Record(dir) => (Some(HttpCache::new(dir)), false),
+            Mode::Replay(dir) => (Some(HttpCache::new(dir)), true),
+        };
+
+        Self {
+            client,
+            settings,
+            cache,
+            snapshot,
+            replay,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// GET a URL and return its body; non-2xx responses are errors
+    pub async fn get_text(&self, url: &str, opts: FetchOptions) -> Result<String, Box<dyn Error>> {
+        Ok(self.get(url, opts).await?.body)
+    }
+
+    /// GET a URL, retrying 429/5xx and transient network errors with backoff.
+    /// URLs disallowed by the host's robots.txt are refused unless `opts` opts out.
+    #[instrument(level = "debug", skip(self, opts))]
+    pub async fn get(&self, url: &str, opts: FetchOptions) -> Result<Page, Box<dyn Error>> {
+        let parsed = Url::parse(url)?;
+        let limiter = self.limiter(&host_key(&parsed));
+
+        let mut interval = self.interval();
+        if opts.respect_robots.unwrap_or(self.settings.respect_robots) {
+            let rules = limiter.robots.get_or_init(|| self.fetch_robots(&parsed, &limiter)).await;
+            if let Some(rule) = rules.blocking_rule(&path_and_query(&parsed)) {
+                warn!(%url, %rule, "Skipping URL disallowed by robots.txt");
+                return Err(format!("{} is disallowed by robots.txt ({})", redact(&parsed), rule).into());
+            }
+            interval = interval.max(rules.crawl_delay.unwrap_or_default());
+        }
+
+        if self.replay {
+            return self.replay(url).await;
+        }
+
+        let cached = match &self.cache {
+            Some(cache) => cache.load(url).await,
+            None => None,
+        };
+
+        let mut attempt: u32 = 0;
+        loop {
+            attempt += 1;
+            let result = {
+                let _permit = limiter.permits.acquire().await?;
+                sleep(limiter.reserve(interval)).await;
+                self.send(&parsed, cached.as_ref()).await
+            };
+
+            let retries_left = attempt <= self.settings.max_retries;
+            match result {
+                Ok(res) if res.status == StatusCode::NOT_MODIFIED && cached.is_some() => {
+                    let cached = cached.expect("checked above");
+                    debug!(attempt, "Not modified; serving cached copy");
+                    let page = Page {
+                        url: Url::parse(&cached.final_url)?,
+                        body: cached.body,
+                    };
+                    self.record(url, &page).await;
+                    return Ok(page);
+                }
+                Ok(res) if res.status.is_success() => {
+                    debug!(status = %res.status, bytes = res.body.len(), attempt, "Fetched");
+                    self.store(url, &res).await;
+                    let page = Page {
+                        url: res.url,
+                        body: res.body,
+                    };
+                    self.record(url, &page).await;
+                    return Ok(page);
+                }
+                Ok(res) if retries_left && is_retryable(res.status) => {
+                    let delay = res
+                        .retry_after
+                        .map(|d| d.min(MAX_RETRY_AFTER))
+                        .unwrap_or_else(|| backoff_delay(attempt));
+                    if res.status == StatusCode::TOO_MANY_REQUESTS {
+                        // Slow every request to this host, not only this one
+                        limiter.push_back(delay);
+                    }
+                    warn!(status = %res.status, attempt, ?delay, preview = %preview(&res.body), "Retryable HTTP status; backing off");
+                    sleep(delay).await;
+                }
+                Ok(res) => {
+                    return Err(format!("{} returned HTTP {}: {}", redact(&parsed), res.status, preview(&res.body)).into());
+                }
+                Err(e) if retries_left && (e.is_timeout() || e.is_connect()) => {
+                    let delay = backoff_delay(attempt);
+                    warn!(error = %e, attempt, ?delay, "Request failed; backing off");
+                    sleep(delay).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// One request, made conditional when a cached copy carries validators
+    async fn send(&self, url: &Url, cached: Option<&CachedResponse>) -> Result<Response, reqwest::Error> {
+        let mut req = self.client.get(url.clone());
+        if let Some(etag) = cached.and_then(|c| c.etag.as_deref()) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(modified) = cached.and_then(|c| c.last_modified.as_deref()) {
+            req = req.header(IF_MODIFIED_SINCE, modified);
+        }
+
+        let res = req.send().await?;
+        let header = |name| res.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let retry_after = header(RETRY_AFTER).as_deref().and_then(parse_retry_after);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let status = res.status();
+        let final_url = res.url().clone();
+
+        Ok(Response {
+            url: final_url,
+            status,
+            retry_after,
+            etag,
+            last_modified,
+            body: res.text().await?,
+        })
+    }
+
+    async fn store(&self, url: &str, res: &Response) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        let entry = CachedResponse {
+            url: url.to_string(),
+            final_url: res.url.to_string(),
+            etag: res.etag.clone(),
+            last_modified: res.last_modified.clone(),
+            fetched_at: Utc::now().to_rfc3339(),
+            body: res.body.clone(),
+        };
+        if let Err(e) = cache.store(&entry).await {
+            warn!(%url, error = %e, "Could not write HTTP cache entry");
+        }
+    }
+
+    /// Save a response for `--replay`
+    async fn record(&self, url: &str, page: &Page) {
+        let Some(snapshot) = self.snapshot.as_ref().filter(|_| !self.replay) else {
+            return;
+        };
+        let entry = CachedResponse {
+            url: snapshot_key(url),
+            final_url: page.url.to_string(),
+            etag: None,
+            last_modified: None,
+            fetched_at: Utc::now().to_rfc3339(),
+            body: page.body.clone(),
+        };
+        if let Err(e) = snapshot.store(&entry).await {
+            warn!(%url, error = %e, "Could not record response");
+        }
+    }
+
+    async fn replay(&self, url: &str) -> Result<Page, Box<dyn Error>> {
+        let entry = match &self.snapshot {
+            Some(snapshot) => snapshot.load(&snapshot_key(url)).await,
+            None => None,
+        };
+        let Some(entry) = entry else {
+            warn!(%url, "URL missing from replay snapshot");
+            return Err(format!("{} was not recorded in the replay snapshot", redact(&Url::parse(url)?)).into());
+        };
+        debug!(%url, bytes = entry.body.len(), "Replayed");
+        Ok(Page {
+            url: Url::parse(&entry.final_url)?,
+            body: entry.body,
+        })
+    }
+
+    /// Fetch and parse a host's robots.txt (once per run; cached on the host limiter)
+    async fn fetch_robots(&self, url: &Url, limiter: &HostLimiter) -> RobotRules {
+        let mut robots_url = url.clone();
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+        robots_url.set_fragment(None);
+
+        if self.replay {
+            // Unrecorded robots.txt means the recording run saw none (or ignored it)
+            return match self.replay(robots_url.as_str()).await {
+                Ok(page) => RobotRules::parse(&page.body, product_token(self.user_agent())),
+                Err(_) => RobotRules::allow_all(),
+            };
+        }
+
+        let res = {
+            let _permit = limiter.permits.acquire().await;
+            sleep(limiter.reserve(self.interval())).await;
+            self.client.get(robots_url.clone()).send().await
+        };
+
+        let rules = match res {
+            Ok(res) if res.status().is_success() => match res.text().await {
+                Ok(text) => {
+                    let page = Page {
+                        url: robots_url.clone(),
+                        body: text,
+                    };
+                    self.record(robots_url.as_str(), &page).await;
+                    RobotRules::parse(&page.body, product_token(self.user_agent()))
+                }
+                Err(e) => {
+                    warn!(url = %robots_url, error = %e, "Could not read robots.txt; treating host as disallowed");
+                    RobotRules::disallow_all()
+                }
+            },
+            Ok(res) if res.status().is_client_error() => RobotRules::allow_all(),
+            Ok(res) => {
+                warn!(url = %robots_url, status = %res.status(), "robots.txt unavailable; treating host as disallowed");
+                RobotRules::disallow_all()
+            }
+            Err(e) => {
+                warn!(url = %robots_url, error = %e, "robots.txt request failed; treating host as disallowed");
+                RobotRules::disallow_all()
+            }
+        };
+        debug!(url = %robots_url, ?rules, "Loaded robots.txt");
+        rules
+    }
+
+    fn user_agent(&self) -> &str {
+        self.settings.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    fn interval(&self) -> Duration {
+        match self.settings.per_host_rps {
+            Some(rps) if rps > 0.0 => Duration::from_secs_f64(1.0 / rps),
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn limiter(&self, host: &str) -> Arc<HostLimiter> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                Arc::new(HostLimiter {
+                    permits: Semaphore::new(self.settings.per_host_concurrency.max(1)),
+                    next_slot: Mutex::new(Instant::now()),
+                    robots: tokio::sync::OnceCell::new(),
+                })
+            })
+            .clone()
+    }
+}
+
+impl HostLimiter {
+    /// Claim the next request slot; returns how long to wait before sending
+    fn reserve(&self, interval: Duration) -> Duration {
+        let mut next = self.next_slot.lock().unwrap();
+        reserve_slot(&mut next, Instant::now(), interval)
+    }
+
+    fn push_back(&self, delay: Duration) {
+        let mut next = self.next_slot.lock().unwrap();
+        *next = (*next).max(Instant::now() + delay);
+    }
+}
+
+fn reserve_slot(next: &mut Instant, now: Instant, interval: Duration) -> Duration {
+    let start = (*next).max(now);
+    *next = start + interval;
+    start - now
+}
+
+/// Rate limits are tracked per host (including port)
+pub fn host_key(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Snapshot key for a URL: secrets such as the NYT `api-key` are blanked so they
+/// never reach disk and a replay works with any key
+fn snapshot_key(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if !parsed.query_pairs().any(|(k, _)| SECRET_PARAMS.contains(&k.as_ref())) {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if SECRET_PARAMS.contains(&k.as_ref()) { "REDACTED".into() } else { v };
+            (k.into_owned(), v.into_owned())
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+/// The part of a URL robots.txt rules are matched against
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    }
+}
+
+/// `awful_text_news/0.1.21 (+https://…)` -> `awful_text_news`
+fn product_token(user_agent: &str) -> &str {
+    user_agent.split(['/', ' ']).next().unwrap_or(user_agent)
+}
+
+pub fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Exponential backoff with a little jitter
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_DELAY);
+    delay + Duration::from_millis(rng().random_range(0..=250))
+}
+
+/// `Retry-After` is either delta-seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = at.signed_duration_since(Utc::now());
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Drop the query string (it may hold API keys) before putting a URL in an error
+fn redact(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.to_string()
+}
+
+fn preview(body: &str) -> String {
+    body.chars().take(200).collect::<String>().replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        // Dates in the past mean "retry now"
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let future = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&future).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn test_backoff_delay_is_capped() {
+        assert!(backoff_delay(1) >= BASE_DELAY);
+        assert!(backoff_delay(2) >= BASE_DELAY * 2);
+        assert!(backoff_delay(40) <= MAX_DELAY + Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_reserve_slot_spaces_requests() {
+        let now = Instant::now();
+        let mut next = now;
+        let interval = Duration::from_millis(500);
+        assert_eq!(reserve_slot(&mut next, now, interval), Duration::ZERO);
+        assert_eq!(reserve_slot(&mut next, now, interval), interval);
+        assert_eq!(reserve_slot(&mut next, now, interval), interval * 2);
+        // An idle host does not bank slots
+        let later = now + Duration::from_secs(10);
+        assert_eq!(reserve_slot(&mut next, later, interval), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_host_key_and_redact() {
+        let url = Url::parse("https://api.nytimes.com/svc/topstories/v2/home.json?api-key=secret").unwrap();
+        assert_eq!(host_key(&url), "api.nytimes.com");
+        assert_eq!(redact(&url), "https://api.nytimes.com/svc/topstories/v2/home.json");
+        assert_eq!(host_key(&Url::parse("http://127.0.0.1:8080/x").unwrap()), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_snapshot_key_blanks_secrets() {
+        assert_eq!(
+            snapshot_key("https://api.nytimes.com/svc/topstories/v2/home.json?api-key=secret"),
+            "https://api.nytimes.com/svc/topstories/v2/home.json?api-key=REDACTED"
+        );
+        let search = "https://www.google.com/search?q=site%3Aapnews.com&num=50";
+        assert_eq!(snapshot_key(search), search);
+    }
+
+    #[tokio::test]
+    async fn test_replay_serves_recorded_pages_only() {
+        let dir = std::env::temp_dir().join(format!("atn_replay_{}", std::process::id()));
+        let snapshot = HttpCache::new(&dir);
+        snapshot
+            .store(&CachedResponse {
+                url: "https://text.npr.org/".to_string(),
+                final_url: "https://text.npr.org/".to_string(),
+                etag: None,
+                last_modified: None,
+                fetched_at: "2025-05-06T14:30:00+00:00".to_string(),
+                body: "<a class=\"topic-title\" href=\"/nx-s1-1\">Story</a>".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let fetcher = Fetcher::new(HttpSettings::default(), Mode::Replay(dir.clone()));
+        let page = fetcher.get("https://text.npr.org/", FetchOptions::default()).await.unwrap();
+        assert!(page.body.contains("topic-title"));
+        assert!(fetcher.get("https://text.npr.org/other", FetchOptions::default()).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_robots_helpers() {
+        assert_eq!(product_token(DEFAULT_USER_AGENT), "awful_text_news");
+        assert_eq!(product_token("example-bot"), "example-bot");
+        let url = Url::parse("https://www.google.com/search?q=site:apnews.com#top").unwrap();
+        assert_eq!(path_and_query(&url), "/search?q=site:apnews.com");
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+/// The rules from one robots.txt that apply to our user agent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotRules {
+    rules: Vec<Rule>,
+    pub crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotRules {
+    /// Used when robots.txt is missing (4xx)
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Used when robots.txt is unreachable (5xx / network error), as RFC 9309 asks
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parse robots.txt, keeping the groups for `agent` (a product token such as
+    /// `awful_text_news`) or the `*` groups if none name it.
+    pub fn parse(text: &str, agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agent_lines = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agent_lines {
+                        groups.push(Group::default());
+                        in_agent_lines = true;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agent_lines = false;
+                    // An empty Disallow allows everything; an empty Allow means nothing
+                    if let Some(group) = groups.last_mut().filter(|_| !value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_agent_lines = false;
+                    if let Some(group) = groups.last_mut() {
+                        group.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|s| s.is_finite() && *s >= 0.0)
+                            .map(Duration::from_secs_f64);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_ascii_lowercase();
+        let named: Vec<&Group> = groups.iter().filter(|g| g.agents.contains(&agent)).collect();
+        let chosen = if named.is_empty() {
+            groups.iter().filter(|g| g.agents.iter().any(|a| a == "*")).collect()
+        } else {
+            named
+        };
+
+        Self {
+            rules: chosen.iter().flat_map(|g| g.rules.iter().cloned()).collect(),
+            crawl_delay: chosen.iter().filter_map(|g| g.crawl_delay).max(),
+        }
+    }
+
+    /// The rule that forbids `path` (path plus query), if any.
+    /// The longest matching pattern wins and Allow wins ties.
+    pub fn blocking_rule(&self, path: &str) -> Option<String> {
+        if path == "/robots.txt" {
+            return None;
+        }
+        let best = self
+            .rules
+            .iter()
+            .filter(|r| pattern_matches(&r.pattern, path))
+            .max_by_key(|r| (r.pattern.len(), r.allow))?;
+        (!best.allow).then(|| format!("Disallow: {}", best.pattern))
+    }
+}
+
+/// robots.txt patterns are prefixes with `*` wildcards and an optional `$` end anchor
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = r#"
+# Example publisher
+User-agent: *
+Disallow: /search
+Disallow: /*.pdf$
+Allow: /search/about
+Crawl-delay: 2
+
+User-agent: BadBot
+User-agent: awful_text_news
+Disallow: /private/
+Allow: /private/press
+Crawl-delay: 5
+"#;
+
+    #[test]
+    fn test_wildcard_group() {
+        let rules = RobotRules::parse(ROBOTS, "some_other_bot");
+        assert_eq!(rules.blocking_rule("/search?q=news").as_deref(), Some("Disallow: /search"));
+        assert_eq!(rules.blocking_rule("/search/about"), None);
+        assert_eq!(rules.blocking_rule("/files/report.pdf").as_deref(), Some("Disallow: /*.pdf$"));
+        assert_eq!(rules.blocking_rule("/files/report.pdf?x=1"), None);
+        assert_eq!(rules.blocking_rule("/world/story"), None);
+        assert_eq!(rules.crawl_delay, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_named_group_replaces_wildcard() {
+        let rules = RobotRules::parse(ROBOTS, "awful_text_news");
+        assert_eq!(rules.blocking_rule("/search"), None);
+        assert_eq!(rules.blocking_rule("/private/notes").as_deref(), Some("Disallow: /private/"));
+        assert_eq!(rules.blocking_rule("/private/press/release"), None);
+        assert_eq!(rules.crawl_delay, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_empty_disallow_and_missing_groups() {
+        let rules = RobotRules::parse("User-agent: *\nDisallow:\n", "awful_text_news");
+        assert_eq!(rules.blocking_rule("/anything"), None);
+        assert_eq!(RobotRules::parse("", "awful_text_news"), RobotRules::allow_all());
+    }
+
+    #[test]
+    fn test_disallow_all_keeps_robots_txt_reachable() {
+        let rules = RobotRules::disallow_all();
+        assert!(rules.blocking_rule("/").is_some());
+        assert!(rules.blocking_rule("/robots.txt").is_none());
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("/a/*/c", "/a/b/c/d"));
+        assert!(!pattern_matches("/a/*/c$", "/a/b/c/d"));
+        assert!(pattern_matches("/a/*/c$", "/a/b/c"));
+        assert!(pattern_matches("/*", "/"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exactly"));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::models::{AwfulNewsArticle, EditionStatus, FrontPage, NewsArticle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+
+/// One line of the journal
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Entry {
+    /// The edition's date and time stamp (with no articles); always the first line
+    Edition(EditionEntry),
+    /// An article scraped for the edition
+    Fetched(NewsArticle),
+    /// A finished analysis, appended as soon as it completes
+    Analyzed(AwfulNewsArticle),
+}
+
+/// `Entry` as written, borrowing instead of owning
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryRef<'a> {
+    Edition(EditionEntryRef<'a>),
+    Fetched(&'a NewsArticle),
+    Analyzed(&'a AwfulNewsArticle),
+}
+
+/// The edition line: the stamp, plus the completed run it re-runs (if any), saved
+/// before the first update overwrites that run's JSON
+#[derive(Debug, Deserialize)]
+struct EditionEntry {
+    #[serde(flatten)]
+    stamp: FrontPage,
+    #[serde(default)]
+    earlier_run: Option<FrontPage>,
+}
+
+/// `EditionEntry` as written
+#[derive(Serialize)]
+struct EditionEntryRef<'a> {
+    #[serde(flatten)]
+    stamp: &'a FrontPage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    earlier_run: Option<&'a FrontPage>,
+}
+
+/// Append-only NDJSON record of an edition in progress, so a crashed run can be resumed
+/// without re-scraping or re-analyzing what already finished
+#[derive(Debug)]
+pub struct Journal {
+    file: File,
+}
+
+/// An interrupted edition read back from a journal
+#[derive(Debug)]
+pub struct Resumed {
+    /// The original stamp, with every article analyzed so far
+    pub front_page: FrontPage,
+    /// Fetched articles that have no analysis yet
+    pub pending: Vec<NewsArticle>,
+    /// The completed run of the same edition this one re-runs, as it was when the run started
+    pub earlier_run: Option<FrontPage>,
+}
+
+impl Journal {
+    /// Start a new journal for `front_page`, replacing any left over from an earlier run.
+    /// `earlier_run` is the completed run of the same edition this one will be reconciled with.
+    #[instrument(level = "info", skip_all, fields(path = %path.as_ref().display()))]
+    pub fn create(
+        path: impl AsRef<Path>,
+        front_page: &FrontPage,
+        fetched: &[NewsArticle],
+        earlier_run: Option<&FrontPage>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let stamp = FrontPage {
+            local_date: front_page.local_date.clone(),
+            time_of_day: front_page.time_of_day.clone(),
+            edition_label: front_page.edition_label.clone(),
+            local_time: front_page.local_time.clone(),
+            published_at: front_page.published_at,
+            status: EditionStatus::default(),
+            articles: Vec::new(),
+            stories: Vec::new(),
+        };
+
+        let mut out = serde_json::to_string(&EntryRef::Edition(EditionEntryRef {
+            stamp: &stamp,
+            earlier_run,
+        }))?;
+        out.push('\n');
+        for article in fetched {
+            out.push_str(&serde_json::to_string(&EntryRef::Fetched(article))?);
+            out.push('\n');
+        }
+        std::fs::write(path, out)?;
+        info!(fetched = fetched.len(), earlier_run = earlier_run.is_some(), "Started run journal");
+        Self::open(path)
+    }
+
+    /// Reopen an existing journal to append to it, terminating a torn last line first
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let torn = std::fs::read(path)?.last().is_some_and(|b| *b != b'\n');
+        let mut file = OpenOptions::new().append(true).open(path)?;
+        if torn {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self { file })
+    }
+
+    /// Append one finished analysis and flush it to disk
+    pub fn record(&mut self, article: &AwfulNewsArticle) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_string(&EntryRef::Analyzed(article))?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// The full run's journal in `dir`: `run_journal.ndjson`, or `run_journal_<edition>.ndjson`
+/// for a run of a named `--edition`, so it never shares a journal with the scheduled run
+pub fn run_journal_path(dir: &Path, edition: Option<&str>) -> PathBuf {
+    match edition {
+        Some(name) => dir.join(format!("run_journal_{}.ndjson", name)),
+        None => dir.join("run_journal.ndjson"),
+    }
+}
+
+/// Delete the journal at `path` once its edition has been written; a missing journal is fine
+pub fn remove(path: &Path) -> Result<(), Box<dyn Error>> {
+    match std::fs::remove_file(path) {
+        Ok(()) => {
+            info!(path = %path.display(), "Removed run journal");
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read an interrupted edition back; `None` if there is no journal at `path`.
+/// Torn lines (the process died mid-write) are skipped.
+#[instrument(level = "info", skip_all, fields(path = %path.display()))]
+pub fn load(path: &Path) -> Result<Option<Resumed>, Box<dyn Error>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut front_page: Option<FrontPage> = None;
+    let mut earlier_run = None;
+    let mut fetched = Vec::new();
+    for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let entry = match serde_json::from_str::<Entry>(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!(line = i + 1, error = %e, "Skipping unreadable journal line");
+                continue;
+            }
+        };
+        match (entry, front_page.as_mut()) {
+            (Entry::Edition(edition), None) => {
+                front_page = Some(edition.stamp);
+                earlier_run = edition.earlier_run;
+            }
+            (Entry::Edition(_), Some(_)) => return Err(format!("{}: more than one edition line", path.display()).into()),
+            (_, None) => return Err(format!("{}: journal does not start with an edition line", path.display()).into()),
+            (Entry::Fetched(article), Some(_)) => fetched.push(article),
+            (Entry::Analyzed(article), Some(front_page)) => front_page.articles.push(article),
+        }
+    }
+
+    let Some(front_page) = front_page else {
+        return Err(format!("{}: journal is empty", path.display()).into());
+    };
+    let done: HashSet<&str> = front_page.articles.iter().filter_map(|a| a.source.as_deref()).collect();
+    let pending: Vec<NewsArticle> = fetched.into_iter().filter(|a| !done.contains(a.source.as_str())).collect();
+    info!(
+        local_date = %front_page.local_date,
+        time_of_day = %front_page.time_of_day,
+        analyzed = front_page.articles.len(),
+        pending = pending.len(),
+        earlier_run = earlier_run.is_some(),
+        "Loaded run journal"
+    );
+    Ok(Some(Resumed {
+        front_page,
+        pending,
+        earlier_run,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EditionState;
+
+    fn article(source: &str, content: &str) -> NewsArticle {
+        NewsArticle {
+            source: source.to_string(),
+            content: content.to_string(),
+            published_at: None,
+        }
+    }
+
+    fn analysis(source: &str) -> AwfulNewsArticle {
+        let mut a: AwfulNewsArticle = serde_json::from_value(crate::mock_llm::article_json("Title: T")).unwrap();
+        a.source = Some(source.to_string());
+        a
+    }
+
+    fn stamp() -> FrontPage {
+        FrontPage::empty("2025-05-06", "evening", "20:30:00")
+    }
+
+    #[test]
+    fn test_resume_skips_analyzed_articles() {
+        let path = std::env::temp_dir().join(format!("atn_journal_{}.ndjson", std::process::id()));
+        let fetched = vec![article("https://a", "A"), article("https://b", "B"), article("https://c", "C")];
+
+        let mut journal = Journal::create(&path, &stamp(), &fetched, None).unwrap();
+        journal.record(&analysis("https://b")).unwrap();
+        drop(journal);
+        // Simulate a crash in the middle of the next write
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"analyzed\": {\"source\": \"https://").unwrap();
+
+        let resumed = load(&path).unwrap().unwrap();
+        assert_eq!(resumed.front_page.local_date, "2025-05-06");
+        assert_eq!(resumed.front_page.time_of_day, "evening");
+        assert_eq!(resumed.front_page.articles.len(), 1);
+        assert!(resumed.earlier_run.is_none());
+        let pending: Vec<&str> = resumed.pending.iter().map(|a| a.source.as_str()).collect();
+        assert_eq!(pending, vec!["https://a", "https://c"]);
+
+        // Appending after the torn line keeps the new entry readable
+        Journal::open(&path).unwrap().record(&analysis("https://c")).unwrap();
+        let resumed = load(&path).unwrap().unwrap();
+        assert_eq!(resumed.front_page.articles.len(), 2);
+        assert_eq!(resumed.pending.len(), 1);
+
+        remove(&path).unwrap();
+        remove(&path).unwrap();
+        assert!(load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_earlier_run_survives_in_edition_line() {
+        let path = std::env::temp_dir().join(format!("atn_journal_earlier_{}.ndjson", std::process::id()));
+        let mut earlier = stamp();
+        earlier.status.state = EditionState::Complete;
+        earlier.articles = vec![analysis("https://a")];
+
+        Journal::create(&path, &stamp(), &[article("https://b", "B")], Some(&earlier)).unwrap();
+        let resumed = load(&path).unwrap().unwrap();
+        assert_eq!(resumed.front_page.local_time, "20:30:00");
+        assert!(resumed.front_page.articles.is_empty());
+        let earlier_run = resumed.earlier_run.unwrap();
+        assert_eq!(earlier_run.status.state, EditionState::Complete);
+        assert_eq!(earlier_run.articles[0].source.as_deref(), Some("https://a"));
+
+        remove(&path).unwrap();
+    }
+
+    #[test]
+    fn test_run_journal_path() {
+        let dir = Path::new("/etc/atn");
+        assert_eq!(run_journal_path(dir, None), Path::new("/etc/atn/run_journal.ndjson"));
+        assert_eq!(run_journal_path(dir, Some("breaking")), Path::new("/etc/atn/run_journal_breaking.ndjson"));
+    }
+
+    #[test]
+    fn test_load_rejects_corrupt_journal() {
+        let path = std::env::temp_dir().join(format!("atn_journal_bad_{}.ndjson", std::process::id()));
+        std::fs::write(&path, "{\"fetched\": {\"source\": \"https://a\", \"content\": \"A\"}}\n").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,18 @@
+pub mod analyzer;
+pub mod api;
+pub mod clustering;
+pub mod dedup;
+pub mod editions;
+pub mod http;
+pub mod journal;
+#[cfg(any(test, feature = "mock-llm"))]
+pub mod mock_llm;
+pub mod models;
+pub mod novelty;
+pub mod outputs;
+pub mod pipeline;
+pub mod scrapers;
+pub mod settings;
+pub mod utils;
+
+pub use pipeline::Pipeline;
@@ -1,23 +1,20 @@
 use awful_aj::{config, config_dir, template};
-use chrono::Local;
+use awful_text_news::analyzer::{cache::AnalysisCache, Analyzer};
+use awful_text_news::editions::Schedule;
+use awful_text_news::models::{FrontPage, NewsArticle};
+use awful_text_news::pipeline::RerunPolicy;
+use awful_text_news::outputs::{json, JsonOutput, MarkdownOutput, Output};
+use awful_text_news::utils::{ensure_writable_dir, read_ndjson, write_atomic, write_ndjson};
+use awful_text_news::{http, journal, scrapers, settings, Pipeline};
 use clap::Parser;
-use itertools::Itertools;
 use std::error::Error;
-use tracing::{debug, error, info, instrument, warn};
+use std::path::{Path, PathBuf};
+use tracing::{debug, error, info, instrument};
 use tracing_subscriber::{fmt as tfmt, EnvFilter};
 
-mod api;
 mod cli;
-mod models;
-mod outputs;
-mod scrapers;
-mod utils;
 
-use api::ask_with_backoff;
-use cli::Cli;
-use models::{AwfulNewsArticle, FrontPage, ImportantDate, ImportantTimeframe, NamedEntity};
-use outputs::{indexes, json, markdown};
-use utils::{ensure_writable_dir, looks_truncated, time_of_day, truncate_for_log};
+use cli::{Cli, Command};
 
 #[tokio::main]
 #[instrument]
@@ -37,221 +34,214 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // Parse CLI
     let args = Cli::parse();
-    debug!(?args.json_output_dir, ?args.markdown_output_dir, "Parsed CLI arguments");
+    debug!(?args.command, ?args.json_output_dir, ?args.markdown_output_dir, "Parsed CLI arguments");
+
+    match &args.command {
+        None => run_all(&args).await?,
+        Some(Command::Fetch { output }) => {
+            let (settings, _) = load_settings(&args)?;
+            let articles = scrapers::fetch_all(&scrapers::registry(&settings, args.nyt_api_key.clone())).await;
+            write_ndjson(output, &articles).await?;
+        }
+        Some(Command::Analyze {
+            input,
+            output,
+            json_output_dir,
+        }) => {
+            if args.append && json_output_dir.is_none() {
+                return Err("--append needs --json-output-dir to find the earlier run".into());
+            }
+            let (settings, conf_file) = load_settings(&args)?;
+            let analyzer = build_analyzer(&settings, &conf_file).await?;
+            let mut pipeline = Pipeline::new(analyzer)
+                .schedule(Schedule::from_settings(&settings.editions)?)
+                .edition(args.edition.clone())
+                .clustering(cluster_threshold(&settings))
+                .journal(format!("{}.journal", output))
+                .resume(args.resume);
+            if let Some(dir) = json_output_dir {
+                pipeline = pipeline.on_rerun(dir, rerun_policy(args.append));
+                if settings.novelty.enabled {
+                    pipeline = pipeline.compare_with(dir, settings.novelty.lookback_days);
+                }
+            }
+
+            let front_page = match pipeline.resume_edition().await {
+                Some(front_page) => front_page,
+                None => {
+                    let articles: Vec<NewsArticle> = read_ndjson(input).await?;
+                    pipeline.analyze_edition(articles).await
+                }
+            };
+            write_atomic(output, serde_json::to_string_pretty(&front_page)?).await?;
+            info!(path = %output, articles = front_page.articles.len(), "Wrote FrontPage JSON");
+            pipeline.clear_journal();
+        }
+        Some(Command::Render {
+            input,
+            json_output_dir,
+            markdown_output_dir,
+        }) => {
+            if json_output_dir.is_none() && markdown_output_dir.is_none() {
+                return Err("render needs --json-output-dir and/or --markdown-output-dir".into());
+            }
+            let front_page = read_front_page(input).await?;
+            if let Some(dir) = json_output_dir {
+                ensure_writable_dir(dir).await?;
+                JsonOutput::new(dir).write(&front_page).await?;
+            }
+            if let Some(dir) = markdown_output_dir {
+                let (settings, _) = load_settings(&args)?;
+                ensure_writable_dir(dir).await?;
+                MarkdownOutput::new(dir)
+                    .hide_carried_over(settings.novelty.hide_carried_over)
+                    .write_page(&front_page)
+                    .await?;
+            }
+        }
+        Some(Command::Publish {
+            input,
+            markdown_output_dir,
+        }) => {
+            let front_page = read_front_page(input).await?;
+            let page = std::path::Path::new(markdown_output_dir).join(MarkdownOutput::page_filename(&front_page));
+            if !page.exists() {
+                return Err(format!("{} does not exist; run `render` first", page.display()).into());
+            }
+            MarkdownOutput::new(markdown_output_dir).update_indexes(&front_page).await?;
+        }
+        Some(Command::Rebuild {
+            json_output_dir,
+            markdown_output_dir,
+        }) => {
+            let (settings, _) = load_settings(&args)?;
+            let editions = json::read_archive(Path::new(json_output_dir)).await?;
+            ensure_writable_dir(markdown_output_dir).await?;
+            MarkdownOutput::new(markdown_output_dir)
+                .hide_carried_over(settings.novelty.hide_carried_over)
+                .rebuild(&editions)
+                .await?;
+        }
+    }
+
+    let elapsed = start_time.elapsed();
+    info!(
+        ?elapsed,
+        secs = elapsed.as_secs(),
+        millis = elapsed.subsec_millis(),
+        "Execution complete"
+    );
+    Ok(())
+}
+
+/// Scrape, analyze and write the edition in one go
+async fn run_all(args: &Cli) -> Result<(), Box<dyn Error>> {
+    // Both are required by clap when no subcommand is given
+    let json_output_dir = args.json_output_dir.as_deref().unwrap_or_default();
+    let markdown_output_dir = args.markdown_output_dir.as_deref().unwrap_or_default();
 
     // Early check: ensure JSON output dir is writable
-    if let Err(e) = ensure_writable_dir(&args.json_output_dir).await {
+    if let Err(e) = ensure_writable_dir(json_output_dir).await {
         error!(
-            path = %args.json_output_dir,
+            path = %json_output_dir,
             error = %e,
             "JSON output directory is not writable (fix perms or choose a different path)"
         );
         return Err(e);
     }
 
-    // ---- Index and fetch articles ----
-    let cnn_urls = scrapers::cnn::index_articles().await?;
-    let npr_urls = scrapers::npr::index_articles().await?;
-    let apnews_urls = scrapers::apnews::index_articles().await?;
-    let aljazeera_urls = scrapers::aljazeera::index_articles().await?;
-    let bbcnews_urls = scrapers::bbcnews::index_articles().await?;
-    let nyt_articles_with_titles = scrapers::nyt::index_articles(args.nyt_api_key.as_deref()).await?;
+    let (settings, conf_file) = load_settings(args)?;
+    let analyzer = build_analyzer(&settings, &conf_file).await?;
+
+    // The journal lives next to config.yaml, like the caches, not in the published output
+    let journal = journal::run_journal_path(conf_file.parent().unwrap_or(Path::new(".")), args.edition.as_deref());
+    let mut pipeline = Pipeline::new(analyzer)
+        .sources(scrapers::registry(&settings, args.nyt_api_key.clone()))
+        .output(JsonOutput::new(json_output_dir))
+        .output(MarkdownOutput::new(markdown_output_dir).hide_carried_over(settings.novelty.hide_carried_over))
+        .schedule(Schedule::from_settings(&settings.editions)?)
+        .edition(args.edition.clone())
+        .clustering(cluster_threshold(&settings))
+        .journal(journal)
+        .resume(args.resume)
+        .on_rerun(json_output_dir, rerun_policy(args.append));
+    if settings.novelty.enabled {
+        pipeline = pipeline.compare_with(json_output_dir, settings.novelty.lookback_days);
+    }
+    pipeline.run().await;
+    Ok(())
+}
+
+/// Resolve config.yaml, load the source settings and set up the shared HTTP client
+fn load_settings(args: &Cli) -> Result<(settings::Settings, PathBuf), Box<dyn Error>> {
+    let conf_file = match &args.config {
+        Some(path) => PathBuf::from(path),
+        None => config_dir()?.join("config.yaml"),
+    };
+    let config_path = conf_file.to_str().expect("Not a valid config filename");
+    let settings = settings::load_settings(config_path)?;
 
-    let cnn_articles = scrapers::cnn::fetch_articles(cnn_urls).await;
-    let npr_articles = scrapers::npr::fetch_articles(npr_urls).await;
-    let apnews_articles = scrapers::apnews::fetch_articles(apnews_urls).await;
-    let aljazeera_articles = scrapers::aljazeera::fetch_articles(aljazeera_urls).await;
-    let bbcnews_articles = scrapers::bbcnews::fetch_articles(bbcnews_urls).await;
-    let nyt_articles = scrapers::nyt::fetch_articles(nyt_articles_with_titles).await;
+    let mut http_settings = settings.http.clone();
+    if http_settings.cache_dir.is_none() {
+        http_settings.cache_dir = conf_file
+            .parent()
+            .map(|dir| dir.join("http_cache").to_string_lossy().into_owned());
+    }
+    let mode = match (&args.record, &args.replay) {
+        (_, Some(dir)) => {
+            info!(dir = %dir, "Replaying HTTP responses from snapshot");
+            http::Mode::Replay(dir.into())
+        }
+        (Some(dir), None) => {
+            info!(dir = %dir, "Recording HTTP responses");
+            http::Mode::Record(dir.into())
+        }
+        (None, None) => http::Mode::Live,
+    };
+    http::init(http_settings, mode);
 
-    let articles = vec![cnn_articles, npr_articles, apnews_articles, aljazeera_articles, bbcnews_articles, nyt_articles]
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>();
-    info!(count = articles.len(), "Total articles to analyze");
+    Ok((settings, conf_file))
+}
 
-    // ---- Load template & config ----
+/// Load the news_parser template and aj config and build the analyzer
+async fn build_analyzer(settings: &settings::Settings, conf_file: &Path) -> Result<Analyzer, Box<dyn Error>> {
+    let config_path = conf_file.to_str().expect("Not a valid config filename");
     let template = template::load_template("news_parser").await?;
     info!("Loaded template: news_parser");
-    let conf_file = config_dir()?.join("config.yaml");
-    let config_path = conf_file.to_str().expect("Not a valid config filename");
     let config = config::load_config(config_path).unwrap();
     info!(config_path, "Loaded configuration");
-    
-    // Wrap config and template in Arc for sharing across parallel tasks
-    use std::sync::Arc;
-    let config = Arc::new(config);
-    let template = Arc::new(template);
 
-    // ---- Build front page ----
-    let local_date = Local::now().date_naive().to_string();
-    let local_time = Local::now().time().to_string();
-    let mut front_page = FrontPage {
-        time_of_day: time_of_day(),
-        local_time,
-        local_date,
-        articles: Vec::new(),
-    };
-    info!(time_of_day = %front_page.time_of_day, local_date = %front_page.local_date, local_time = %front_page.local_time, "FrontPage initialized");
-
-    // ---- Analyze articles in parallel (12 at a time) ----
-    use futures::stream::{self, StreamExt};
-    const PARALLEL_BATCH_SIZE: usize = 12;
-    
-    let total_articles = articles.len();
-    info!(parallel_batch_size = PARALLEL_BATCH_SIZE, "Starting parallel article processing");
-    
-    // Process articles concurrently
-    let results: Vec<Option<AwfulNewsArticle>> = stream::iter(articles.iter().enumerate())
-        .map(|(i, article)| {
-            let config = Arc::clone(&config);
-            let template = Arc::clone(&template);
-            async move {
-                debug!(index = i, source = %article.source, "Analyzing article");
-
-                // First ask
-                match ask_with_backoff(&config, &article.content, &template).await {
-                    Ok(response_json) => {
-                        // Try parse
-                        let mut parsed = serde_json::from_str::<AwfulNewsArticle>(&response_json);
-
-                        // If the parse failed due to EOF (truncation), re-ask ONCE
-                        if let Err(ref e) = parsed {
-                            if looks_truncated(e) {
-                                warn!(index = i, error = %e, "EOF while parsing; re-asking once");
-                                match ask_with_backoff(&config, &article.content, &template).await {
-                                    Ok(r2) => {
-                                        parsed = serde_json::from_str::<AwfulNewsArticle>(&r2);
-                                    }
-                                    Err(e2) => {
-                                        warn!(index = i, error = %e2, "Re-ask failed; will skip article");
-                                    }
-                                }
-                            }
-                        }
-
-                        match parsed {
-                            Ok(mut awful_news_article) => {
-                                awful_news_article.source = Some(article.source.clone());
-                                awful_news_article.content = Some(article.content.clone());
-
-                                // dedupe
-                                awful_news_article.namedEntities = awful_news_article
-                                    .namedEntities
-                                    .into_iter()
-                                    .unique_by(|e| e.name.clone())
-                                    .collect::<Vec<NamedEntity>>();
-                                awful_news_article.importantDates = awful_news_article
-                                    .importantDates
-                                    .into_iter()
-                                    .unique_by(|e| e.descriptionOfWhyDateIsRelevant.clone())
-                                    .collect::<Vec<ImportantDate>>();
-                                awful_news_article.importantTimeframes = awful_news_article
-                                    .importantTimeframes
-                                    .into_iter()
-                                    .unique_by(|e| e.descriptionOfWhyTimeFrameIsRelevant.clone())
-                                    .collect::<Vec<ImportantTimeframe>>();
-                                awful_news_article.keyTakeAways = awful_news_article
-                                    .keyTakeAways
-                                    .into_iter()
-                                    .unique()
-                                    .collect::<Vec<String>>();
-
-                                info!(index = i, "Successfully processed article");
-                                Some(awful_news_article)
-                            }
-                            Err(e) => {
-                                warn!(
-                                    index = i,
-                                    error = %e,
-                                    response_preview = %truncate_for_log(&response_json, 300),
-                                    "Model returned non-conforming JSON; skipping article"
-                                );
-                                None
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!(index = i, source = %article.source, error = %e, "API call failed; skipping article");
-                        None
-                    }
-                }
-            }
-        })
-        .buffer_unordered(PARALLEL_BATCH_SIZE)
-        .collect()
-        .await;
-
-    // Add successful results to front_page
-    for result in results.into_iter().flatten() {
-        front_page.articles.push(result);
-    }
-    
-    info!(
-        total = total_articles,
-        successful = front_page.articles.len(),
-        failed = total_articles - front_page.articles.len(),
-        "Completed parallel article processing"
-    );
-
-    // Write final JSON after all articles processed
-    if let Err(e) = json::write_frontpage(&front_page, &args.json_output_dir).await {
-        error!(error = %e, "Failed to write final JSON");
-    }
-
-    // ---- Markdown output ----
-    let md = markdown::front_page_to_markdown(&front_page);
-    let output_markdown_filename = format!(
-        "{}/{}_{}.md",
-        args.markdown_output_dir, front_page.local_date, front_page.time_of_day
-    );
+    let analysis_cache = settings.analyzer.cache.then(|| {
+        let dir = match &settings.analyzer.cache_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => conf_file.parent().unwrap_or(Path::new(".")).join("analysis_cache"),
+        };
+        info!(dir = %dir.display(), "Using analysis cache");
+        AnalysisCache::new(dir)
+    });
+    Ok(Analyzer::new(config, template)
+        .with_cache(analysis_cache)
+        .with_max_input_tokens(settings.analyzer.max_input_tokens)
+        .with_max_reasks(settings.analyzer.max_reasks))
+}
 
-    info!(path = %output_markdown_filename, "Writing Markdown");
-    if let Err(e) = tokio::fs::write(&output_markdown_filename, md).await {
-        error!(path = %output_markdown_filename, error = %e, "Failed writing Markdown");
+/// `--replace` is the default, so only `--append` needs checking
+fn rerun_policy(append: bool) -> RerunPolicy {
+    if append {
+        RerunPolicy::Append
     } else {
-        info!(path = %output_markdown_filename, "Wrote FrontPage Markdown");
-    }
-
-    // ---- Index updates ----
-    let markdown_filename = format!("{}_{}.md", front_page.local_date, front_page.time_of_day);
-    
-    if let Err(e) = indexes::update_date_toc_file(
-        &args.markdown_output_dir,
-        &front_page,
-        &markdown_filename,
-    )
-    .await
-    {
-        error!(error = %e, "Failed to update date TOC file");
-    }
-
-    if let Err(e) = indexes::update_summary_md(
-        &args.markdown_output_dir,
-        &front_page,
-        &markdown_filename,
-    )
-    .await
-    {
-        error!(error = %e, "Failed to update SUMMARY.md");
+        RerunPolicy::Replace
     }
+}
 
-    if let Err(e) = indexes::update_daily_news_index(
-        &args.markdown_output_dir,
-        &front_page,
-        &markdown_filename,
-    )
-    .await
-    {
-        error!(error = %e, "Failed to update daily_news.md index");
-    }
+fn cluster_threshold(settings: &settings::Settings) -> Option<f64> {
+    settings.clustering.enabled.then_some(settings.clustering.threshold)
+}
 
-    let elapsed = start_time.elapsed();
-    info!(
-        ?elapsed,
-        secs = elapsed.as_secs(),
-        millis = elapsed.subsec_millis(),
-        "Execution complete"
-    );
-    Ok(())
+/// A `FrontPage` JSON written by `analyze`
+async fn read_front_page(path: &str) -> Result<FrontPage, Box<dyn Error>> {
+    let json = tokio::fs::read_to_string(path).await?;
+    let front_page: FrontPage = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))?;
+    info!(path, articles = front_page.articles.len(), "Loaded FrontPage");
+    Ok(front_page)
 }
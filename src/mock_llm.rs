@@ -0,0 +1,353 @@
+use awful_aj::{config::AwfulJadeConfig, template::ChatTemplate};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// One chat-completions request as the mock received it
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub model: String,
+    pub messages: Vec<Value>,
+    pub stream: bool,
+}
+
+impl Request {
+    /// Content of the last user message (the article text, for the analyzer)
+    pub fn user(&self) -> &str {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m["role"] == "user")
+            .and_then(|m| m["content"].as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// What the mock answers with
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    content: String,
+    finish_reason: &'static str,
+    delay: Duration,
+}
+
+impl Reply {
+    /// A normal completion whose message content is `content`
+    pub fn content(content: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            content: content.into(),
+            finish_reason: "stop",
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// A completion cut off after `chars` characters, as when the model hits its token limit
+    pub fn truncated(content: impl Into<String>, chars: usize) -> Self {
+        let content: String = content.into().chars().take(chars).collect();
+        Self {
+            finish_reason: "length",
+            ..Self::content(content)
+        }
+    }
+
+    /// An OpenAI-style error response
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            ..Self::content("")
+        }
+    }
+
+    /// Hold the response back for `delay`
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+type Responder = dyn Fn(&Request) -> Reply + Send + Sync;
+
+struct State {
+    script: Mutex<VecDeque<Reply>>,
+    responder: Box<Responder>,
+    requests: Mutex<Vec<Request>>,
+}
+
+/// OpenAI-compatible `/chat/completions` server on localhost for tests.
+///
+/// Scripted replies are served first, in order; after that every request goes to the
+/// responder. Supports both plain and `stream: true` (SSE) requests.
+pub struct MockLlm {
+    addr: SocketAddr,
+    state: Arc<State>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockLlm {
+    /// Start a server that answers every request with `responder`
+    pub async fn start(responder: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock LLM");
+        let addr = listener.local_addr().expect("mock LLM address");
+        let state = Arc::new(State {
+            script: Mutex::new(VecDeque::new()),
+            responder: Box::new(responder),
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, &state).await;
+                });
+            }
+        });
+
+        Self { addr, state, server }
+    }
+
+    /// Start a server that answers with a schema-valid analysis of whatever it is sent
+    pub async fn echo_articles() -> Self {
+        Self::start(|req| Reply::content(article_json(req.user()).to_string())).await
+    }
+
+    /// Queue replies to serve before falling back to the responder
+    pub fn script(self, replies: impl IntoIterator<Item = Reply>) -> Self {
+        self.state.script.lock().unwrap().extend(replies);
+        self
+    }
+
+    pub fn api_base(&self) -> String {
+        format!("http://{}/v1", self.addr)
+    }
+
+    /// Every request received so far, in arrival order
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// An `AwfulJadeConfig` pointed at this server
+    pub fn config(&self) -> AwfulJadeConfig {
+        let yaml = format!(
+            "api_key: mock\napi_base: {}\nmodel: mock-model\ncontext_max_tokens: 8192\n\
+             assistant_minimum_context_tokens: 2048\nstop_words: []\nsession_db_url: ':memory:'\n",
+            self.api_base()
+        );
+        serde_yaml::from_str(&yaml).expect("mock LLM config")
+    }
+}
+
+impl Drop for MockLlm {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// The checked-in `news_parser` template
+pub fn news_parser_template() -> ChatTemplate {
+    serde_yaml::from_str(include_str!("../template/news_parser.yaml")).expect("news_parser template")
+}
+
+/// A schema-valid `news_parser` analysis; the title is taken from a `Title:` line if present
+pub fn article_json(input: &str) -> Value {
+    let title = input
+        .lines()
+        .find_map(|l| l.strip_prefix("Title:"))
+        .map(str::trim)
+        .unwrap_or("Mock headline");
+    json!({
+        "title": title,
+        "dateOfPublication": "2025-05-06",
+        "timeOfPublication": "14:30:00",
+        "category": "Politics & Governance",
+        "summaryOfNewsArticle": format!("Summary of {}.", title),
+        "keyTakeAways": [format!("{} happened", title)],
+        "namedEntities": [{
+            "name": "Mock Entity",
+            "whatIsThisEntity": "A placeholder",
+            "whyIsThisEntityRelevantToTheArticle": "It is in every mock article"
+        }],
+        "importantDates": [],
+        "importantTimeframes": [],
+        "tags": ["mock"]
+    })
+}
+
+async fn serve(mut stream: TcpStream, state: &State) -> Result<(), Box<dyn Error>> {
+    let body = read_request(&mut stream).await?;
+    let raw: Value = serde_json::from_slice(&body)?;
+    let request = Request {
+        model: raw["model"].as_str().unwrap_or_default().to_string(),
+        messages: raw["messages"].as_array().cloned().unwrap_or_default(),
+        stream: raw["stream"].as_bool().unwrap_or(false),
+    };
+    state.requests.lock().unwrap().push(request.clone());
+
+    let scripted = state.script.lock().unwrap().pop_front();
+    let reply = scripted.unwrap_or_else(|| (state.responder)(&request));
+    tokio::time::sleep(reply.delay).await;
+
+    let (content_type, payload) = if reply.status != 200 {
+        let error = json!({"error": {"message": format!("mock error {}", reply.status), "type": "server_error"}});
+        ("application/json", error.to_string())
+    } else if request.stream {
+        ("text/event-stream", sse_body(&request.model, &reply))
+    } else {
+        ("application/json", completion(&request.model, &reply).to_string())
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} MOCK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reply.status,
+        content_type,
+        payload.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(payload.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read one HTTP/1.1 request and return its body
+async fn read_request(stream: &mut TcpStream) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err("connection closed before headers".into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_ascii_lowercase();
+    let length: usize = headers
+        .lines()
+        .find_map(|l| l.strip_prefix("content-length:"))
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0);
+
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(buf.split_off(header_end))
+}
+
+fn completion(model: &str, reply: &Reply) -> Value {
+    json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 0,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": reply.content},
+            "finish_reason": reply.finish_reason
+        }],
+        "usage": {"prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0}
+    })
+}
+
+/// The content as a series of `chat.completion.chunk` server-sent events
+fn sse_body(model: &str, reply: &Reply) -> String {
+    let chunk = |delta: Value, finish_reason: Value| {
+        let event = json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        });
+        format!("data: {}\n\n", event)
+    };
+
+    let chars: Vec<char> = reply.content.chars().collect();
+    let mut body = chunk(json!({"role": "assistant", "content": ""}), Value::Null);
+    for piece in chars.chunks(16) {
+        body.push_str(&chunk(json!({"content": piece.iter().collect::<String>()}), Value::Null));
+    }
+    body.push_str(&chunk(json!({}), json!(reply.finish_reason)));
+    body.push_str("data: [DONE]\n\n");
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn post(mock: &MockLlm, body: Value) -> (u16, String) {
+        let resp = reqwest::Client::new()
+            .post(format!("{}/chat/completions", mock.api_base()))
+            .body(body.to_string())
+            .header("content-type", "application/json")
+            .send()
+            .await
+            .unwrap();
+        (resp.status().as_u16(), resp.text().await.unwrap())
+    }
+
+    fn chat(user: &str, stream: bool) -> Value {
+        json!({"model": "m", "stream": stream, "messages": [{"role": "user", "content": user}]})
+    }
+
+    #[tokio::test]
+    async fn test_script_then_responder() {
+        let mock = MockLlm::start(|req| Reply::content(format!("echo {}", req.user())))
+            .await
+            .script([Reply::status(503), Reply::truncated("{\"title\": \"abc\"}", 8)]);
+
+        let (status, _) = post(&mock, chat("one", false)).await;
+        assert_eq!(status, 503);
+
+        let (_, body) = post(&mock, chat("two", false)).await;
+        let v: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(v["choices"][0]["message"]["content"], "{\"title\"");
+        assert_eq!(v["choices"][0]["finish_reason"], "length");
+
+        let (_, body) = post(&mock, chat("three", false)).await;
+        let v: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(v["choices"][0]["message"]["content"], "echo three");
+
+        let users: Vec<String> = mock.requests().iter().map(|r| r.user().to_string()).collect();
+        assert_eq!(users, vec!["one", "two", "three"]);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_reassembles() {
+        let mock = MockLlm::echo_articles().await;
+        let (status, body) = post(&mock, chat("Title: Streamed\n\nBody", true)).await;
+        assert_eq!(status, 200);
+
+        let content: String = body
+            .lines()
+            .filter_map(|l| l.strip_prefix("data: "))
+            .filter(|d| *d != "[DONE]")
+            .filter_map(|d| serde_json::from_str::<Value>(d).ok())
+            .filter_map(|v| v["choices"][0]["delta"]["content"].as_str().map(str::to_string))
+            .collect();
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap(), article_json("Title: Streamed"));
+    }
+
+    #[tokio::test]
+    async fn test_delay() {
+        let mock = MockLlm::echo_articles().await.script([Reply::content("{}").delayed(Duration::from_millis(200))]);
+        let t0 = std::time::Instant::now();
+        post(&mock, chat("x", false)).await;
+        assert!(t0.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -1,17 +1,176 @@
+use crate::utils::upcase;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct NewsArticle {
     pub source: String,
     pub content: String,
+    /// When the outlet says the article was published, if the scraper could parse it
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "rfc3339")]
+    pub published_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Serde for an optional timestamp as an RFC 3339 string (chrono is built without serde)
+mod rfc3339 {
+    use chrono::{DateTime, FixedOffset};
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(at: &Option<DateTime<FixedOffset>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match at {
+            Some(at) => serializer.serialize_str(&at.to_rfc3339()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| DateTime::parse_from_rfc3339(&s).map_err(D::Error::custom))
+            .transpose()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FrontPage {
     pub local_date: String,
+    /// Edition name from the schedule; historically always morning, afternoon or evening
     pub time_of_day: String,
+    /// Edition heading in the indexes; `time_of_day` capitalized when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition_label: Option<String>,
     pub local_time: String,
+    /// When the run started, with the publication's UTC offset; RFC 3339 in JSON
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "rfc3339")]
+    pub published_at: Option<DateTime<FixedOffset>>,
+    /// How far analysis has got; editions written before this existed read as complete
+    #[serde(default)]
+    pub status: EditionStatus,
     pub articles: Vec<AwfulNewsArticle>,
+    /// Articles grouped by the event they report; empty until the edition is complete
+    #[serde(default)]
+    pub stories: Vec<Story>,
+}
+
+/// What makes two runs the same edition: its date and its name (time of day). A re-run
+/// with the same identity replaces the earlier run's files and index entries
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EditionId {
+    pub date: String,
+    pub name: String,
+}
+
+impl fmt::Display for EditionId {
+    /// `<date>_<name>`, the stem of the edition's Markdown page
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.date, self.name)
+    }
+}
+
+/// One event and every article in the edition that reports it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Story {
+    /// Headline of the lead article
+    pub title: String,
+    pub category: String,
+    /// Indices into `FrontPage.articles`, lead article first
+    pub articles: Vec<usize>,
+    /// Outlets that covered the story (see `AwfulNewsArticle::source_tag`)
+    pub outlets: Vec<String>,
+}
+
+/// Whether an edition is still being analyzed, and how many of its articles are done
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EditionStatus {
+    pub state: EditionState,
+    /// Articles attempted so far, including the ones the model could not handle
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditionState {
+    InProgress,
+    #[default]
+    Complete,
+}
+
+#[cfg(test)]
+impl FrontPage {
+    /// A complete edition with no articles or stories, for tests
+    pub fn empty(local_date: &str, time_of_day: &str, local_time: &str) -> Self {
+        Self {
+            local_date: local_date.to_string(),
+            time_of_day: time_of_day.to_string(),
+            edition_label: None,
+            local_time: local_time.to_string(),
+            published_at: None,
+            status: EditionStatus::default(),
+            articles: Vec::new(),
+            stories: Vec::new(),
+        }
+    }
+}
+
+impl FrontPage {
+    /// Sorts editions in the order they were published: by date, then by when the run
+    /// started. The date can be the day before the run for one that went past midnight.
+    pub fn publication_order(&self) -> (String, NaiveDateTime) {
+        let started = self
+            .published_at
+            .map(|at| at.naive_local())
+            .or_else(|| NaiveDateTime::parse_from_str(&format!("{} {}", self.local_date, self.local_time), "%Y-%m-%d %H:%M:%S%.f").ok())
+            .unwrap_or_default();
+        (self.local_date.clone(), started)
+    }
+
+    /// The edition's heading in the indexes
+    pub fn label(&self) -> String {
+        self.edition_label.clone().unwrap_or_else(|| upcase(&self.time_of_day))
+    }
+
+    pub fn id(&self) -> EditionId {
+        EditionId {
+            date: self.local_date.clone(),
+            name: self.time_of_day.clone(),
+        }
+    }
+
+    /// Stories grouped by category in alphabetical order. Without clustering results
+    /// (an edition in progress, or one written before stories existed) every article
+    /// is its own story. With `hide_carried_over`, stories whose articles were all in an
+    /// earlier edition unchanged are left out.
+    pub fn stories_by_category(&self, hide_carried_over: bool) -> BTreeMap<String, Vec<Story>> {
+        let stories = if self.stories.is_empty() {
+            self.articles
+                .iter()
+                .enumerate()
+                .map(|(i, article)| Story {
+                    title: article.title.clone(),
+                    category: article.category.clone(),
+                    articles: vec![i],
+                    outlets: article.source_tag().into_iter().collect(),
+                })
+                .collect()
+        } else {
+            self.stories.clone()
+        };
+
+        let mut by_category: BTreeMap<String, Vec<Story>> = BTreeMap::new();
+        for story in stories {
+            let carried_over = story
+                .articles
+                .iter()
+                .all(|&i| self.articles.get(i).is_some_and(|a| a.novelty == Some(Novelty::CarriedOver)));
+            if hide_carried_over && carried_over {
+                continue;
+            }
+            by_category.entry(story.category.clone()).or_default().push(story);
+        }
+        by_category
+    }
 }
 
 #[allow(non_snake_case)]
@@ -29,9 +188,40 @@ pub struct AwfulNewsArticle {
     pub importantTimeframes: Vec<ImportantTimeframe>,
     pub tags: Vec<String>,
     pub content: Option<String>,
+    /// Publication time: the one the scraper found, else the model's `dateOfPublication`
+    /// and `timeOfPublication` when they carry a UTC offset; RFC 3339 in JSON
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "rfc3339")]
+    pub publishedAt: Option<DateTime<FixedOffset>>,
+    /// How the article compares to earlier editions; unset when nothing was compared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub novelty: Option<Novelty>,
+}
+
+/// An article compared to the ones in earlier editions
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Novelty {
+    /// Not in any earlier edition
+    New,
+    /// In an earlier edition, but its text has changed since
+    Updated,
+    /// In an earlier edition with the same text
+    CarriedOver,
 }
 
 impl AwfulNewsArticle {
+    /// Fill in `publishedAt`, preferring the scraped timestamp over the model's reading
+    /// of the article. A model time without a UTC offset is no instant, so it is left out.
+    pub fn normalize_published_at(&mut self, scraped: Option<DateTime<FixedOffset>>) {
+        self.publishedAt = scraped.or_else(|| self.model_published_at());
+    }
+
+    /// `dateOfPublication` and `timeOfPublication` as a timestamp, if they make one
+    fn model_published_at(&self) -> Option<DateTime<FixedOffset>> {
+        let date = NaiveDate::parse_from_str(self.dateOfPublication.trim(), "%Y-%m-%d").ok()?;
+        DateTime::parse_from_rfc3339(&format!("{}T{}", date, self.timeOfPublication.trim())).ok()
+    }
+
     /// Extract the domain name (before .com/.org/etc) from the source URL
     /// For example: "https://lite.cnn.com/article" -> "cnn"
     pub fn source_tag(&self) -> Option<String> {
@@ -85,6 +275,7 @@ mod tests {
         let article = NewsArticle {
             source: "https://example.com".to_string(),
             content: "Test content".to_string(),
+            published_at: None,
         };
         assert_eq!(article.source, "https://example.com");
         assert_eq!(article.content, "Test content");
@@ -93,15 +284,18 @@ mod tests {
     #[test]
     fn test_frontpage_serialization() {
         let frontpage = FrontPage {
-            local_date: "2025-05-06".to_string(),
-            time_of_day: "evening".to_string(),
-            local_time: "20:30:00".to_string(),
-            articles: vec![],
+            status: EditionStatus {
+                state: EditionState::InProgress,
+                processed: 3,
+                total: 10,
+            },
+            ..FrontPage::empty("2025-05-06", "evening", "20:30:00")
         };
 
         let json = serde_json::to_string(&frontpage).unwrap();
         assert!(json.contains("2025-05-06"));
         assert!(json.contains("evening"));
+        assert!(json.contains(r#""status":{"state":"in_progress","processed":3,"total":10}"#));
     }
 
     #[test]
@@ -117,6 +311,27 @@ mod tests {
         assert_eq!(frontpage.local_date, "2025-05-06");
         assert_eq!(frontpage.time_of_day, "morning");
         assert_eq!(frontpage.articles.len(), 0);
+        assert_eq!(frontpage.status.state, EditionState::Complete);
+    }
+
+    #[test]
+    fn test_publication_order_after_midnight() {
+        let edition = |time_of_day: &str, local_time: &str, published_at: Option<&str>| -> FrontPage {
+            serde_json::from_value(serde_json::json!({
+                "local_date": "2025-05-06",
+                "time_of_day": time_of_day,
+                "local_time": local_time,
+                "published_at": published_at,
+                "articles": []
+            }))
+            .unwrap()
+        };
+        let morning = edition("morning", "07:00:00.123", None);
+        let evening = edition("evening", "20:00:00", Some("2025-05-06T20:00:00-04:00"));
+        let late = edition("late", "01:15:00", Some("2025-05-07T01:15:00-04:00"));
+
+        assert!(morning.publication_order() < evening.publication_order());
+        assert!(evening.publication_order() < late.publication_order());
     }
 
     #[test]
@@ -138,6 +353,8 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec!["politics".to_string(), "news".to_string()],
             content: Some("Full content".to_string()),
+            publishedAt: None,
+            novelty: None,
         };
 
         assert_eq!(article.title, "Test Article");
@@ -197,6 +414,8 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec![],
             content: None,
+            publishedAt: None,
+            novelty: None,
         };
 
         assert_eq!(article.source_tag(), Some("cnn".to_string()));
@@ -217,6 +436,8 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec![],
             content: None,
+            publishedAt: None,
+            novelty: None,
         };
 
         assert_eq!(article.source_tag(), Some("npr".to_string()));
@@ -237,6 +458,8 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec![],
             content: None,
+            publishedAt: None,
+            novelty: None,
         };
 
         assert_eq!(article.source_tag(), None);
@@ -257,8 +480,47 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec![],
             content: None,
+            publishedAt: None,
+            novelty: None,
         };
 
         assert_eq!(article.source_tag(), Some("example".to_string()));
     }
+
+    #[test]
+    fn test_normalize_published_at() {
+        let published_at = |date: &str, time: &str, scraped: Option<&str>| {
+            let mut article: AwfulNewsArticle = serde_json::from_value(serde_json::json!({
+                "source": null, "dateOfPublication": date, "timeOfPublication": time,
+                "title": "T", "category": "Law & Justice", "summaryOfNewsArticle": "S",
+                "keyTakeAways": [], "namedEntities": [], "importantDates": [],
+                "importantTimeframes": [], "tags": [], "content": null
+            }))
+            .unwrap();
+            article.normalize_published_at(scraped.map(|s| DateTime::parse_from_rfc3339(s).unwrap()));
+            article.publishedAt.map(|at| at.to_rfc3339())
+        };
+
+        let scraped = Some("2025-05-06T09:15:00.5-04:00");
+        assert_eq!(published_at("2025-05-06", "14:30:00", scraped).as_deref(), Some("2025-05-06T09:15:00.500-04:00"));
+        assert_eq!(published_at("2025-05-06", "02:03:00.000Z", None).as_deref(), Some("2025-05-06T02:03:00+00:00"));
+        assert_eq!(published_at("2025-05-06", "14:30:00+02:00", None).as_deref(), Some("2025-05-06T14:30:00+02:00"));
+        // Without an offset the time could be anywhere, so there is no instant to give
+        assert_eq!(published_at("2025-05-06", "14:30:00", None), None);
+        assert_eq!(published_at("2025-05-06", "14:30", None), None);
+        assert_eq!(published_at("Unknown", "14:30:00", None), None);
+        assert_eq!(published_at("2025-05-06", "this morning", None), None);
+    }
+
+    #[test]
+    fn test_news_article_published_at_round_trip() {
+        let json = r#"{"source":"https://example.com","content":"C","published_at":"2025-05-06T09:15:00-04:00"}"#;
+        let article: NewsArticle = serde_json::from_str(json).unwrap();
+        assert_eq!(article.published_at.unwrap().to_rfc3339(), "2025-05-06T09:15:00-04:00");
+        assert_eq!(serde_json::to_string(&article).unwrap(), json);
+
+        let undated: NewsArticle = serde_json::from_str(r#"{"source":"s","content":"c"}"#).unwrap();
+        assert!(undated.published_at.is_none());
+        assert!(!serde_json::to_string(&undated).unwrap().contains("published_at"));
+    }
 }
@@ -0,0 +1,185 @@
+use crate::dedup::{canonical_url, near, simhash};
+use crate::models::{FrontPage, Novelty};
+use crate::outputs::json::read_editions;
+use crate::utils::stable_hash;
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// An article body reduced to what is compared across editions
+#[derive(Clone, Copy)]
+struct Text {
+    /// Hash of the whitespace-normalized text
+    exact: u64,
+    simhash: Option<u64>,
+}
+
+impl Text {
+    fn new(content: &str) -> Self {
+        let normalized = content.split_whitespace().collect::<Vec<_>>().join(" ");
+        Self {
+            exact: stable_hash(normalized.as_bytes()),
+            simhash: simhash(content),
+        }
+    }
+
+    fn same_as(&self, other: &Text) -> bool {
+        self.exact == other.exact || matches!((self.simhash, other.simhash), (Some(a), Some(b)) if near(a, b))
+    }
+}
+
+/// Earlier editions written under `json_output_dir` in the last `lookback_days` days
+/// (1 = the current day only), oldest first. The current edition and anything stamped
+/// after it are left out; unreadable files are skipped.
+#[instrument(level = "info", skip_all, fields(dir = %json_output_dir.display(), lookback_days))]
+pub async fn load_previous(json_output_dir: &Path, current: &FrontPage, lookback_days: u32) -> Vec<FrontPage> {
+    let Ok(today) = current.local_date.parse::<NaiveDate>() else {
+        warn!(local_date = %current.local_date, "Edition date is not YYYY-MM-DD; not comparing with earlier editions");
+        return Vec::new();
+    };
+
+    let mut previous = Vec::new();
+    for days_back in 0..lookback_days {
+        let dir = json_output_dir.join((today - Duration::days(days_back.into())).to_string());
+        for front_page in read_editions(&dir).await {
+            let same_edition = front_page.id() == current.id();
+            let later = front_page.publication_order() >= current.publication_order();
+            if !same_edition && !later {
+                previous.push(front_page);
+            }
+        }
+    }
+    previous.sort_by_cached_key(FrontPage::publication_order);
+    info!(editions = previous.len(), "Loaded earlier editions");
+    previous
+}
+
+/// Mark every article as new, updated or carried over compared to `previous`.
+///
+/// An article whose URL appeared before is carried over if its text is the same (or
+/// nearly so) and updated otherwise; under a new URL, the same text still counts as
+/// carried over.
+#[instrument(level = "info", skip_all, fields(previous = previous.len()))]
+pub fn mark(front_page: &mut FrontPage, previous: &[FrontPage]) {
+    // Newest edition wins for a URL seen more than once
+    let mut by_url: HashMap<String, Option<Text>> = HashMap::new();
+    let mut texts: Vec<Text> = Vec::new();
+    for article in previous.iter().flat_map(|fp| &fp.articles) {
+        let text = article.content.as_deref().map(Text::new);
+        texts.extend(text);
+        if let Some(source) = &article.source {
+            by_url.insert(canonical_url(source), text);
+        }
+    }
+
+    for article in &mut front_page.articles {
+        let text = article.content.as_deref().map(Text::new);
+        let earlier = article.source.as_deref().and_then(|s| by_url.get(&canonical_url(s)));
+        let novelty = match (earlier, text) {
+            (Some(Some(before)), Some(now)) if !now.same_as(before) => Novelty::Updated,
+            (Some(_), _) => Novelty::CarriedOver,
+            (None, Some(now)) if texts.iter().any(|t| now.same_as(t)) => Novelty::CarriedOver,
+            (None, _) => Novelty::New,
+        };
+        article.novelty = Some(novelty);
+    }
+
+    let count = |n: Novelty| front_page.articles.iter().filter(|a| a.novelty == Some(n)).count();
+    info!(
+        new = count(Novelty::New),
+        updated = count(Novelty::Updated),
+        carried_over = count(Novelty::CarriedOver),
+        "Compared with earlier editions"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AwfulNewsArticle;
+
+    const BODY: &str = "The Federal Reserve kept its benchmark interest rate unchanged on Wednesday, saying that \
+        uncertainty over tariffs had grown and that the risks of both higher unemployment and higher \
+        inflation had risen. Officials gave no signal of when rates might be cut and markets were mixed \
+        after the decision was announced in Washington by the chair.";
+
+    fn article(source: &str, content: &str) -> AwfulNewsArticle {
+        let mut a: AwfulNewsArticle = serde_json::from_value(crate::mock_llm::article_json("Title: T")).unwrap();
+        a.source = Some(source.to_string());
+        a.content = Some(content.to_string());
+        a
+    }
+
+    fn edition(time_of_day: &str, local_time: &str, articles: Vec<AwfulNewsArticle>) -> FrontPage {
+        FrontPage {
+            articles,
+            ..FrontPage::empty("2025-05-06", time_of_day, local_time)
+        }
+    }
+
+    #[test]
+    fn test_mark() {
+        let morning = edition(
+            "morning",
+            "07:00:00",
+            vec![
+                article("https://apnews.com/article/rates?utm_source=google", BODY),
+                article("https://lite.cnn.com/ship", "Title: Ship\n\nThe ship sank."),
+                article("https://text.npr.org/nx-1", "Title: Quake\n\nA small earthquake hit."),
+            ],
+        );
+        let mut afternoon = edition(
+            "afternoon",
+            "12:00:00",
+            vec![
+                article("https://www.apnews.com/article/rates", &format!("{}  ", BODY)),
+                article("https://lite.cnn.com/ship", "Title: Ship\n\nThe ship sank. Divers found the wreck."),
+                article("https://text.npr.org/nx-2", "Title: Election\n\nVoting began."),
+                article("https://text.npr.org/nx-9", "Title: Quake\n\nA small  earthquake hit."),
+            ],
+        );
+
+        mark(&mut afternoon, &[morning]);
+        let marks: Vec<Option<Novelty>> = afternoon.articles.iter().map(|a| a.novelty).collect();
+        assert_eq!(
+            marks,
+            vec![
+                Some(Novelty::CarriedOver),
+                Some(Novelty::Updated),
+                Some(Novelty::New),
+                Some(Novelty::CarriedOver)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_previous_skips_current_and_later_editions() {
+        let dir = std::env::temp_dir().join(format!("atn_novelty_{}", std::process::id()));
+        let write = |date: &str, name: &str, front_page: &FrontPage| {
+            let path = dir.join(date);
+            std::fs::create_dir_all(&path).unwrap();
+            std::fs::write(path.join(name), serde_json::to_string(front_page).unwrap()).unwrap();
+        };
+        let mut yesterday = edition("evening", "20:00:00", vec![]);
+        yesterday.local_date = "2025-05-05".to_string();
+        write("2025-05-05", "evening.json", &yesterday);
+        write("2025-05-06", "morning.json", &edition("morning", "07:00:00", vec![]));
+        write("2025-05-06", "afternoon.json", &edition("afternoon", "12:00:00", vec![]));
+        write("2025-05-06", "evening.json", &edition("evening", "20:00:00", vec![]));
+        std::fs::write(dir.join("2025-05-06").join("broken.json"), "{").unwrap();
+
+        let current = edition("afternoon", "12:30:00", vec![]);
+        let today: Vec<String> = load_previous(&dir, &current, 1).await.into_iter().map(|fp| fp.time_of_day).collect();
+        assert_eq!(today, vec!["morning"]);
+
+        let two_days: Vec<String> = load_previous(&dir, &current, 2)
+            .await
+            .into_iter()
+            .map(|fp| format!("{} {}", fp.local_date, fp.time_of_day))
+            .collect();
+        assert_eq!(two_days, vec!["2025-05-05 evening", "2025-05-06 morning"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
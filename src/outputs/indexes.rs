@@ -1,56 +1,63 @@
+use super::MarkdownOutput;
 use crate::models::FrontPage;
-use crate::utils::{slugify_title, upcase};
+use crate::utils::{slugify_title, write_atomic};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::fmt::Write;
 use std::path::Path;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
 use tracing::{info, instrument};
 
-/// Update the date-specific table of contents file
-#[instrument(level = "info", skip_all, fields(%markdown_output_dir, date = %front_page.local_date, file = %markdown_filename))]
-pub async fn update_date_toc_file(
-    markdown_output_dir: &str,
-    front_page: &FrontPage,
-    markdown_filename: &str,
-) -> Result<(), Box<dyn Error>> {
-    let toc_path = format!("{}/{}.md", markdown_output_dir, front_page.local_date);
-    let mut toc_md = String::new();
+/// `SUMMARY.md` as first written, before any edition is linked
+const SUMMARY_HEADER: &str =
+    "# Summary\n\n[Home](./home.md)\n- [PGP](./pgp.md)\n- [Contact](./contact.md)\n- [Daily News](./daily_news.md)\n";
 
-    if !Path::new(&toc_path).exists() {
-        writeln!(
-            toc_md,
-            "# Editions published on {}\n",
-            front_page.local_date
-        )
-        .unwrap();
-    }
+/// `daily_news.md` as first written, before any edition is linked
+const DAILY_NEWS_HEADER: &str = "# Awful News Index\n\n";
+
+/// Heading at the top of a date's table of contents
+pub fn date_toc_header(date: &str) -> String {
+    format!("# Editions published on {}\n\n", date)
+}
+
+/// One edition's entry in its date's table of contents: the edition link, then its
+/// categories and stories
+pub fn edition_toc(front_page: &FrontPage, markdown_filename: &str, hide_carried_over: bool) -> String {
+    let mut toc_md = String::new();
 
     writeln!(
         toc_md,
         "- [{}](./{})",
-        upcase(&front_page.time_of_day),
+        front_page.label(),
         markdown_filename
     )
     .unwrap();
 
-    // Group articles by category
-    use std::collections::BTreeMap;
-    let mut articles_by_category: BTreeMap<String, Vec<&crate::models::AwfulNewsArticle>> = BTreeMap::new();
-    
-    for article in &front_page.articles {
-        articles_by_category
-            .entry(article.category.clone())
-            .or_insert_with(Vec::new)
-            .push(article);
-    }
-
-    // Write articles organized by category (alphabetically)
-    for (category, articles) in articles_by_category {
+    // Write stories organized by category (alphabetically)
+    for (category, stories) in front_page.stories_by_category(hide_carried_over) {
         let category_slug = slugify_title(&category);
         writeln!(toc_md, "\t- [**{}**]({}#{})", category, markdown_filename, category_slug).unwrap();
-        
-        for article in articles {
+
+        for story in stories {
+            let article = match story.articles[..] {
+                [only] => front_page.articles.get(only),
+                _ => None,
+            };
+            let Some(article) = article else {
+                // A story from several outlets links to its own heading
+                writeln!(
+                    toc_md,
+                    "\t\t- [{}]({}#{}) <small>`{}`</small>",
+                    story.title,
+                    markdown_filename,
+                    slugify_title(&story.title),
+                    story.outlets.join("`, `")
+                )
+                .unwrap();
+                continue;
+            };
+
             let mut slug = slugify_title(&article.title);
             let source_tag = article.source_tag()
                 .map(|tag| format!(" <small>`{}`</small>", tag))
@@ -71,16 +78,61 @@ pub async fn update_date_toc_file(
         }
     }
 
-    let mut file = tokio::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&toc_path)
-        .await?;
-    file.write_all(toc_md.as_bytes()).await?;
+    toc_md
+}
+
+/// Update the date-specific table of contents file, replacing the entry an earlier run
+/// of the same edition left there
+#[instrument(level = "info", skip_all, fields(%markdown_output_dir, date = %front_page.local_date, file = %markdown_filename))]
+pub async fn update_date_toc_file(
+    markdown_output_dir: &str,
+    front_page: &FrontPage,
+    markdown_filename: &str,
+    hide_carried_over: bool,
+) -> Result<(), Box<dyn Error>> {
+    let toc_path = format!("{}/{}.md", markdown_output_dir, front_page.local_date);
+    let block = edition_toc(front_page, markdown_filename, hide_carried_over);
+
+    let toc_md = match fs::read_to_string(&toc_path).await {
+        Ok(toc) => replace_edition_block(&toc, markdown_filename, &block),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => date_toc_header(&front_page.local_date) + &block,
+        Err(e) => return Err(e.into()),
+    };
+    write_atomic(&toc_path, toc_md).await?;
     info!(path = %toc_path, "Updated TOC file");
     Ok(())
 }
 
+/// `toc` with the edition block linking `markdown_filename` replaced by `block`, or with
+/// `block` added at the end. Further copies, appended by runs before re-runs replaced
+/// their entry, are dropped.
+fn replace_edition_block(toc: &str, markdown_filename: &str, block: &str) -> String {
+    let link = format!("](./{})", markdown_filename);
+    let mut toc_md = String::with_capacity(toc.len() + block.len());
+    let mut replaced = false;
+    let mut in_edition = false;
+    for line in toc.split_inclusive('\n') {
+        // Each edition block starts with its unindented link line
+        if line.starts_with("- [") {
+            in_edition = line.contains(&link);
+            if in_edition && !replaced {
+                toc_md.push_str(block);
+                replaced = true;
+            }
+        }
+        if !in_edition {
+            toc_md.push_str(line);
+        }
+    }
+    if !replaced {
+        if !toc_md.is_empty() && !toc_md.ends_with('\n') {
+            toc_md.push('\n');
+        }
+        toc_md.push_str(block);
+    }
+    toc_md
+}
+
 /// Update the SUMMARY.md file for mdBook navigation
 #[instrument(level = "info", skip_all, fields(%markdown_output_dir, date = %front_page.local_date, file = %markdown_filename))]
 pub async fn update_summary_md(
@@ -94,7 +146,7 @@ pub async fn update_summary_md(
     if Path::new(&summary_path).exists() {
         summary = fs::read_to_string(&summary_path).await?;
     } else {
-        summary.push_str("# Summary\n\n[Home](./home.md)\n- [PGP](./pgp.md)\n- [Contact](./contact.md)\n- [Daily News](./daily_news.md)\n");
+        summary.push_str(SUMMARY_HEADER);
     }
 
     let date_heading = format!(
@@ -103,7 +155,7 @@ pub async fn update_summary_md(
     );
     let edition_heading = format!(
         "        - [{}](./{})",
-        upcase(&front_page.time_of_day),
+        front_page.label(),
         markdown_filename
     );
 
@@ -116,7 +168,9 @@ pub async fn update_summary_md(
             let mut j = i + 1;
             let mut found_edition = false;
             while j < lines.len() && lines[j].starts_with("        - ") {
-                if lines[j].trim() == edition_heading.trim() {
+                // Matched on the link so a relabelled edition replaces its line
+                if lines[j].contains(&format!("](./{})", markdown_filename)) {
+                    lines[j] = edition_heading.clone();
                     found_edition = true;
                     break;
                 }
@@ -157,7 +211,7 @@ pub async fn update_daily_news_index(
     if Path::new(&index_path).exists() {
         content = fs::read_to_string(&index_path).await?;
     } else {
-        content.push_str("# Awful News Index\n\n");
+        content.push_str(DAILY_NEWS_HEADER);
     }
 
     let date_heading = format!(
@@ -166,7 +220,7 @@ pub async fn update_daily_news_index(
     );
     let edition_entry = format!(
         "    - [{}](./{})",
-        upcase(&front_page.time_of_day),
+        front_page.label(),
         markdown_filename
     );
 
@@ -179,7 +233,9 @@ pub async fn update_daily_news_index(
             let mut j = i + 1;
             let mut found_edition = false;
             while j < lines.len() && lines[j].starts_with("    - ") {
-                if lines[j].trim() == edition_entry.trim() {
+                // Matched on the link so a relabelled edition replaces its line
+                if lines[j].contains(&format!("](./{})", markdown_filename)) {
+                    lines[j] = edition_entry.clone();
                     found_edition = true;
                     break;
                 }
@@ -213,3 +269,180 @@ pub async fn update_daily_news_index(
     info!(path = %index_path, "Updated daily_news.md index");
     Ok(())
 }
+
+/// Editions grouped by date, newest date last and each date's editions in publishing
+/// order. Of two editions with the same date and name, the later one is kept.
+pub(crate) fn by_date(editions: &[FrontPage]) -> BTreeMap<&str, Vec<&FrontPage>> {
+    let mut dates: BTreeMap<&str, Vec<&FrontPage>> = BTreeMap::new();
+    for front_page in editions {
+        dates.entry(front_page.local_date.as_str()).or_default().push(front_page);
+    }
+    for day in dates.values_mut() {
+        day.sort_by_cached_key(|fp| (fp.publication_order(), fp.time_of_day.clone()));
+        let mut seen = HashSet::new();
+        let mut latest_first: Vec<&FrontPage> = day.drain(..).rev().filter(|fp| seen.insert(fp.time_of_day.as_str())).collect();
+        latest_first.reverse();
+        *day = latest_first;
+    }
+    dates
+}
+
+/// Whether an index line links to a date TOC (`./YYYY-MM-DD.md`) or an edition page
+/// (`./YYYY-MM-DD_<edition>.md`)
+fn is_edition_link(line: &str) -> bool {
+    let Some(start) = line.find("](./") else {
+        return false;
+    };
+    let target = line[start + 4..].split(')').next().unwrap_or_default();
+    let (Some(date), Some(rest)) = (target.get(..10), target.get(10..)) else {
+        return false;
+    };
+    date.parse::<NaiveDate>().is_ok() && (rest == ".md" || (rest.starts_with('_') && rest.ends_with(".md")))
+}
+
+/// A date's whole table of contents, as `update_date_toc_file` builds it edition by edition
+pub fn date_toc(date: &str, editions: &[&FrontPage], hide_carried_over: bool) -> String {
+    let mut toc_md = date_toc_header(date);
+    for front_page in editions {
+        toc_md.push_str(&edition_toc(front_page, &MarkdownOutput::page_filename(front_page), hide_carried_over));
+    }
+    toc_md
+}
+
+/// `SUMMARY.md` linking exactly `editions`, newest date first. Lines of `existing` that
+/// are not date or edition links are kept; the links go after the Daily News entry.
+pub fn summary_md(existing: Option<&str>, editions: &[FrontPage]) -> String {
+    let mut lines: Vec<String> = existing
+        .unwrap_or(SUMMARY_HEADER)
+        .lines()
+        .filter(|l| !is_edition_link(l))
+        .map(|l| l.to_string())
+        .collect();
+
+    let mut links = Vec::new();
+    for (date, day) in by_date(editions).into_iter().rev() {
+        links.push(format!("    - [{}](./{}.md)", date, date));
+        for front_page in day {
+            links.push(format!(
+                "        - [{}](./{})",
+                front_page.label(),
+                MarkdownOutput::page_filename(front_page)
+            ));
+        }
+    }
+
+    let at = lines.iter().position(|l| l.contains("- [Daily News]")).map_or(lines.len(), |pos| pos + 1);
+    lines.splice(at..at, links);
+    lines.join("\n")
+}
+
+/// `daily_news.md` linking exactly `editions`, newest date first. Lines of `existing`
+/// that are not date or edition links are kept, after the index.
+pub fn daily_news_md(existing: Option<&str>, editions: &[FrontPage]) -> String {
+    let kept: Vec<&str> = existing.unwrap_or(DAILY_NEWS_HEADER).lines().filter(|l| !is_edition_link(l)).collect();
+    let header_end = kept.iter().position(|l| l.starts_with("# Awful News Index")).map_or(0, |pos| pos + 1);
+    let mut lines: Vec<String> = kept[..header_end].iter().map(|l| l.to_string()).collect();
+
+    for (date, day) in by_date(editions).into_iter().rev() {
+        lines.push(String::new());
+        lines.push(format!("- [**{}**](./{}.md)", date, date));
+        for front_page in day {
+            lines.push(format!(
+                "    - [{}](./{})",
+                front_page.label(),
+                MarkdownOutput::page_filename(front_page)
+            ));
+        }
+    }
+
+    let rest: Vec<&str> = kept[header_end..]
+        .iter()
+        .copied()
+        .skip_while(|l| l.trim().is_empty())
+        .collect();
+    let rest_len = rest.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |pos| pos + 1);
+    if rest_len > 0 {
+        lines.push(String::new());
+        lines.extend(rest[..rest_len].iter().map(|l| l.to_string()));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editions() -> Vec<FrontPage> {
+        vec![
+            FrontPage::empty("2025-05-06", "evening", "20:00:00"),
+            FrontPage::empty("2025-05-05", "morning", "07:00:00"),
+            FrontPage::empty("2025-05-06", "morning", "07:00:00"),
+        ]
+    }
+
+    #[test]
+    fn test_summary_md() {
+        let existing = "# Summary\n\n[Home](./home.md)\n- [Daily News](./daily_news.md)\n    - [2025-05-01](./2025-05-01.md)\n        - [Morning](./2025-05-01_morning.md)\n- [About](./about.md)";
+        let summary = summary_md(Some(existing), &editions());
+        assert_eq!(
+            summary,
+            "# Summary\n\n[Home](./home.md)\n- [Daily News](./daily_news.md)\n\
+             \x20   - [2025-05-06](./2025-05-06.md)\n\
+             \x20       - [Morning](./2025-05-06_morning.md)\n\
+             \x20       - [Evening](./2025-05-06_evening.md)\n\
+             \x20   - [2025-05-05](./2025-05-05.md)\n\
+             \x20       - [Morning](./2025-05-05_morning.md)\n\
+             - [About](./about.md)"
+        );
+        assert_eq!(summary_md(Some(&summary), &editions()), summary);
+    }
+
+    #[test]
+    fn test_daily_news_md() {
+        let index = daily_news_md(None, &editions());
+        assert_eq!(
+            index,
+            "# Awful News Index\n\n\
+             - [**2025-05-06**](./2025-05-06.md)\n\
+             \x20   - [Morning](./2025-05-06_morning.md)\n\
+             \x20   - [Evening](./2025-05-06_evening.md)\n\n\
+             - [**2025-05-05**](./2025-05-05.md)\n\
+             \x20   - [Morning](./2025-05-05_morning.md)\n"
+        );
+        assert_eq!(daily_news_md(Some(&index), &editions()), index);
+    }
+
+    #[test]
+    fn test_rerun_replaces_edition_block() {
+        let morning = edition_toc(&FrontPage::empty("2025-05-06", "morning", "07:00:00"), "2025-05-06_morning.md", false);
+        let evening = edition_toc(&FrontPage::empty("2025-05-06", "evening", "20:00:00"), "2025-05-06_evening.md", false);
+        let toc = format!("{}{}{}", date_toc_header("2025-05-06"), morning, evening);
+
+        let rerun = "- [Morning](./2025-05-06_morning.md)\n\t- [**World**](2025-05-06_morning.md#world)\n";
+        let replaced = replace_edition_block(&toc, "2025-05-06_morning.md", rerun);
+        assert_eq!(replaced, format!("{}{}{}", date_toc_header("2025-05-06"), rerun, evening));
+        assert_eq!(replace_edition_block(&replaced, "2025-05-06_morning.md", rerun), replaced);
+
+        // Copies appended by older runs collapse into one
+        let duplicated = format!("{}{}", toc, morning);
+        assert_eq!(replace_edition_block(&duplicated, "2025-05-06_morning.md", &morning), toc);
+
+        let night = "- [Night](./2025-05-06_night.md)\n";
+        assert_eq!(replace_edition_block(&toc, "2025-05-06_night.md", night), format!("{}{}", toc, night));
+    }
+
+    #[test]
+    fn test_date_toc_keeps_latest_copy_of_an_edition() {
+        let mut all = editions();
+        all.push(FrontPage::empty("2025-05-06", "morning", "08:00:00"));
+        let dates = by_date(&all);
+        let day = &dates["2025-05-06"];
+        let times: Vec<&str> = day.iter().map(|fp| fp.local_time.as_str()).collect();
+        assert_eq!(times, vec!["08:00:00", "20:00:00"]);
+        assert_eq!(
+            date_toc("2025-05-06", day, false),
+            "# Editions published on 2025-05-06\n\n- [Morning](./2025-05-06_morning.md)\n- [Evening](./2025-05-06_evening.md)\n"
+        );
+    }
+}
@@ -1,42 +1,92 @@
-use crate::models::FrontPage;
-use chrono::{Duration, Local, NaiveTime};
+use crate::models::{EditionId, FrontPage};
+use crate::utils::write_atomic;
+use chrono::NaiveDate;
 use std::error::Error;
+use std::path::{Path, PathBuf};
 use tokio::fs;
-use tracing::{error, info, instrument};
+use tracing::{error, info, instrument, warn};
 
-/// Write FrontPage to JSON file with date-based directory structure
+/// Write FrontPage to `<date>/<name>.json` under `json_output_dir`, filed under the
+/// edition's own date like every other output
 #[instrument(level = "info", skip_all, fields(json_output_dir = %json_output_dir))]
 pub async fn write_frontpage(
     front_page: &FrontPage,
     json_output_dir: &str,
 ) -> Result<(), Box<dyn Error>> {
     let json = serde_json::to_string(front_page)?;
+    let path = edition_path(Path::new(json_output_dir), &front_page.id());
 
-    let midnight = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
-    let now = Local::now().time();
-    let yesterday = Local::now().date_naive() - Duration::days(1);
+    if let Some(full_json_dir) = path.parent() {
+        info!(full_json_dir = %full_json_dir.display(), "Ensuring JSON directory exists");
+        if let Err(e) = fs::create_dir_all(full_json_dir).await {
+            error!(full_json_dir = %full_json_dir.display(), error = %e, "Failed to create JSON dir");
+            return Err(e.into());
+        }
+    }
 
-    let full_json_dir = if front_page.time_of_day == "evening" && (now >= midnight) {
-        format!("{}/{}", json_output_dir, yesterday.to_string())
-    } else {
-        format!("{}/{}", json_output_dir, front_page.local_date)
-    };
+    let output_json_filename = path.to_string_lossy();
+    info!(path = %output_json_filename, "Writing JSON");
+    write_atomic(&output_json_filename, json).await?;
+    info!(path = %output_json_filename, processed = front_page.status.processed, total = front_page.status.total, "Wrote JSON API file");
 
-    info!(%full_json_dir, "Ensuring JSON directory exists");
-    if let Err(e) = fs::create_dir_all(&full_json_dir).await {
-        error!(%full_json_dir, error = %e, "Failed to create JSON dir");
-        return Err(e.into());
-    }
+    Ok(())
+}
+
+/// `<json_output_dir>/<date>/<name>.json`, where an edition's JSON is written
+pub fn edition_path(json_output_dir: &Path, id: &EditionId) -> PathBuf {
+    json_output_dir.join(&id.date).join(format!("{}.json", id.name))
+}
 
-    let output_json_filename = if front_page.time_of_day == "evening" && (now >= midnight) {
-        format!("{}/{}.json", full_json_dir, yesterday.to_string())
-    } else {
-        format!("{}/{}.json", full_json_dir, front_page.time_of_day)
+/// The edition an earlier run wrote under `json_output_dir`, if there is a readable one
+pub async fn read_edition(json_output_dir: &Path, id: &EditionId) -> Option<FrontPage> {
+    let path = edition_path(json_output_dir, id);
+    let bytes = fs::read(&path).await.ok()?;
+    serde_json::from_slice(&bytes)
+        .inspect_err(|e| warn!(path = %path.display(), error = %e, "Skipping unreadable edition"))
+        .ok()
+}
+
+/// Every edition JSON in one date directory, in no particular order; unreadable files are skipped
+pub async fn read_editions(dir: &Path) -> Vec<FrontPage> {
+    let mut editions = Vec::new();
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return editions;
     };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let parsed = match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice::<FrontPage>(&bytes).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match parsed {
+            Ok(front_page) => editions.push(front_page),
+            Err(e) => warn!(path = %path.display(), error = %e, "Skipping unreadable edition"),
+        }
+    }
+    editions
+}
 
-    info!(path = %output_json_filename, "Writing JSON");
-    fs::write(&output_json_filename, json).await?;
-    info!(path = %output_json_filename, "Wrote JSON API file");
+/// Every edition under `json_output_dir`, read from its `YYYY-MM-DD` directories and
+/// sorted oldest first
+#[instrument(level = "info", skip_all, fields(dir = %json_output_dir.display()))]
+pub async fn read_archive(json_output_dir: &Path) -> Result<Vec<FrontPage>, Box<dyn Error>> {
+    let mut dates = Vec::new();
+    let mut entries = fs::read_dir(json_output_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.parse::<NaiveDate>().is_ok() && entry.file_type().await?.is_dir() {
+            dates.push(entry.path());
+        }
+    }
 
-    Ok(())
+    let mut editions = Vec::new();
+    for dir in dates {
+        editions.extend(read_editions(&dir).await);
+    }
+    editions.sort_by_cached_key(|fp| (fp.publication_order(), fp.time_of_day.clone()));
+    info!(editions = editions.len(), "Read JSON archive");
+    Ok(editions)
 }
@@ -1,140 +1,156 @@
-use crate::models::FrontPage;
+use crate::models::{AwfulNewsArticle, FrontPage, Novelty};
+use chrono::SecondsFormat;
 use std::fmt::Write;
 use tracing::{debug, instrument};
 
-/// Convert a FrontPage to Markdown format
+/// Convert a FrontPage to Markdown format, optionally leaving out stories carried over unchanged
 #[instrument(level = "debug", skip_all)]
-pub fn front_page_to_markdown(front_page: &FrontPage) -> String {
+pub fn front_page_to_markdown(front_page: &FrontPage, hide_carried_over: bool) -> String {
     let mut md = String::new();
 
     writeln!(md, "# Awful Times\n").unwrap();
     writeln!(md, "#### Edition published at {}\n", front_page.local_time).unwrap();
 
-    // Group articles by category
-    use std::collections::BTreeMap;
-    let mut articles_by_category: BTreeMap<String, Vec<&crate::models::AwfulNewsArticle>> = BTreeMap::new();
-    
-    for article in &front_page.articles {
-        articles_by_category
-            .entry(article.category.clone())
-            .or_insert_with(Vec::new)
-            .push(article);
-    }
-
     // Process each category in alphabetical order
-    for (category, articles) in articles_by_category {
+    for (category, stories) in front_page.stories_by_category(hide_carried_over) {
         writeln!(md, "# {}\n", category).unwrap();
 
-        for article in articles {
-            // Title with source tag
-            if let Some(tag) = article.source_tag() {
+        for story in stories {
+            let articles: Vec<&AwfulNewsArticle> =
+                story.articles.iter().filter_map(|&i| front_page.articles.get(i)).collect();
+            if let [article] = articles[..] {
+                write_article(&mut md, article, 2);
+            } else {
+                // One story from several outlets: the story, then each outlet's article under it
+                writeln!(md, "## {}\n", story.title).unwrap();
                 writeln!(
                     md,
-                    "## {} - <small>`{}`</small>\n",
-                    article.title, tag
+                    "- <small>covered by {} outlets: `{}`</small>\n",
+                    story.outlets.len(),
+                    story.outlets.join("`, `")
                 )
                 .unwrap();
-            } else {
-                writeln!(md, "## {}\n", article.title).unwrap();
+                for article in articles {
+                    write_article(&mut md, article, 3);
+                }
             }
 
-            // Source link
-            if let Some(source) = &article.source {
-                writeln!(md, "- [source]({})", source).unwrap();
-            }
+            writeln!(md, "---\n").unwrap();
+        }
+    }
 
-            // Publication date/time
-            writeln!(
-                md,
-                "- _Published: {} {}_",
-                article.dateOfPublication, article.timeOfPublication
-            )
-            .unwrap();
+    debug!(chars = md.len(), "Rendered Markdown length");
+    md
+}
 
-            // Category
-            writeln!(md, "- **{}**", article.category).unwrap();
+/// One article with its title at heading `level` and its sections one level below
+fn write_article(md: &mut String, article: &AwfulNewsArticle, level: usize) {
+    let title = "#".repeat(level);
+    let section = "#".repeat(level + 1);
 
-            // Tags
-            if !article.tags.is_empty() {
-                let tags_str = article.tags.join(", ");
-                writeln!(md, "- <small>tags: `{}`</small>\n", tags_str).unwrap();
-            } else {
-                writeln!(md).unwrap();
-            }
+    // Title with source tag
+    if let Some(tag) = article.source_tag() {
+        writeln!(md, "{} {} - <small>`{}`</small>\n", title, article.title, tag).unwrap();
+    } else {
+        writeln!(md, "{} {}\n", title, article.title).unwrap();
+    }
 
-            // Summary
-            writeln!(md, "### Summary\n").unwrap();
-            writeln!(md, "{}\n", article.summaryOfNewsArticle.trim()).unwrap();
+    // Source link
+    if let Some(source) = &article.source {
+        writeln!(md, "- [source]({})", source).unwrap();
+    }
 
-        if !article.keyTakeAways.is_empty() {
-            writeln!(md, "### Key Takeaways").unwrap();
-            for takeaway in &article.keyTakeAways {
-                writeln!(md, "  - {}", takeaway).unwrap();
-            }
-            writeln!(md).unwrap();
-        }
+    // Publication date/time, as the model read it when nothing better was found
+    match &article.publishedAt {
+        Some(published_at) => writeln!(md, "- _Published: {}_", published_at.to_rfc3339_opts(SecondsFormat::Secs, false)).unwrap(),
+        None => writeln!(
+            md,
+            "- _Published: {} {}_",
+            article.dateOfPublication, article.timeOfPublication
+        )
+        .unwrap(),
+    }
 
-        if !article.namedEntities.is_empty() {
-            writeln!(md, "### Named Entities").unwrap();
-            for entity in &article.namedEntities {
-                writeln!(md, "- **{}**", entity.name).unwrap();
-                writeln!(md, "    - {}", entity.whatIsThisEntity).unwrap();
-                writeln!(md, "    - {}", entity.whyIsThisEntityRelevantToTheArticle).unwrap();
-            }
-            writeln!(md).unwrap();
-        }
+    // Category
+    writeln!(md, "- **{}**", article.category).unwrap();
 
-        if !article.importantDates.is_empty() {
-            writeln!(md, "### Important Dates").unwrap();
-            for date in &article.importantDates {
-                writeln!(md, "  - **{}**", date.dateMentionedInArticle).unwrap();
-                writeln!(md, "    - {}", date.descriptionOfWhyDateIsRelevant).unwrap();
-            }
-            writeln!(md).unwrap();
+    // Compared with earlier editions
+    match article.novelty {
+        Some(Novelty::New) => writeln!(md, "- <small>new in this edition</small>").unwrap(),
+        Some(Novelty::Updated) => writeln!(md, "- <small>updated since an earlier edition</small>").unwrap(),
+        Some(Novelty::CarriedOver) => writeln!(md, "- <small>carried over from an earlier edition</small>").unwrap(),
+        None => {}
+    }
+
+    // Tags
+    if !article.tags.is_empty() {
+        let tags_str = article.tags.join(", ");
+        writeln!(md, "- <small>tags: `{}`</small>\n", tags_str).unwrap();
+    } else {
+        writeln!(md).unwrap();
+    }
+
+    // Summary
+    writeln!(md, "{} Summary\n", section).unwrap();
+    writeln!(md, "{}\n", article.summaryOfNewsArticle.trim()).unwrap();
+
+    if !article.keyTakeAways.is_empty() {
+        writeln!(md, "{} Key Takeaways", section).unwrap();
+        for takeaway in &article.keyTakeAways {
+            writeln!(md, "  - {}", takeaway).unwrap();
         }
+        writeln!(md).unwrap();
+    }
 
-        if !article.importantTimeframes.is_empty() {
-            writeln!(md, "### Important Timeframes").unwrap();
-            for timeframe in &article.importantTimeframes {
-                writeln!(
-                    md,
-                    "  - **From _{}_ to _{}_**",
-                    timeframe.approximateTimeFrameStart, timeframe.approximateTimeFrameEnd
-                )
-                .unwrap();
-                writeln!(
-                    md,
-                    "    - {}",
-                    timeframe.descriptionOfWhyTimeFrameIsRelevant
-                )
-                .unwrap();
-            }
-            writeln!(md).unwrap();
+    if !article.namedEntities.is_empty() {
+        writeln!(md, "{} Named Entities", section).unwrap();
+        for entity in &article.namedEntities {
+            writeln!(md, "- **{}**", entity.name).unwrap();
+            writeln!(md, "    - {}", entity.whatIsThisEntity).unwrap();
+            writeln!(md, "    - {}", entity.whyIsThisEntityRelevantToTheArticle).unwrap();
         }
+        writeln!(md).unwrap();
+    }
 
-            writeln!(md, "---\n").unwrap();
+    if !article.importantDates.is_empty() {
+        writeln!(md, "{} Important Dates", section).unwrap();
+        for date in &article.importantDates {
+            writeln!(md, "  - **{}**", date.dateMentionedInArticle).unwrap();
+            writeln!(md, "    - {}", date.descriptionOfWhyDateIsRelevant).unwrap();
         }
+        writeln!(md).unwrap();
     }
 
-    debug!(chars = md.len(), "Rendered Markdown length");
-    md
+    if !article.importantTimeframes.is_empty() {
+        writeln!(md, "{} Important Timeframes", section).unwrap();
+        for timeframe in &article.importantTimeframes {
+            writeln!(
+                md,
+                "  - **From _{}_ to _{}_**",
+                timeframe.approximateTimeFrameStart, timeframe.approximateTimeFrameEnd
+            )
+            .unwrap();
+            writeln!(
+                md,
+                "    - {}",
+                timeframe.descriptionOfWhyTimeFrameIsRelevant
+            )
+            .unwrap();
+        }
+        writeln!(md).unwrap();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::AwfulNewsArticle;
+    use crate::models::Story;
 
     #[test]
     fn test_empty_frontpage_markdown() {
-        let frontpage = FrontPage {
-            local_date: "2025-05-06".to_string(),
-            time_of_day: "evening".to_string(),
-            local_time: "20:30:00".to_string(),
-            articles: vec![],
-        };
+        let frontpage = FrontPage::empty("2025-05-06", "evening", "20:30:00");
 
-        let md = front_page_to_markdown(&frontpage);
+        let md = front_page_to_markdown(&frontpage, false);
         assert!(md.contains("# Awful Times"));
         assert!(md.contains("20:30:00"));
     }
@@ -154,21 +170,96 @@ mod tests {
             importantTimeframes: vec![],
             tags: vec!["tech".to_string(), "science".to_string()],
             content: None,
+            publishedAt: chrono::DateTime::parse_from_rfc3339("2025-05-06T14:30:00.25-04:00").ok(),
+            novelty: None,
         };
 
         let frontpage = FrontPage {
-            local_date: "2025-05-06".to_string(),
-            time_of_day: "morning".to_string(),
-            local_time: "08:00:00".to_string(),
             articles: vec![article],
+            ..FrontPage::empty("2025-05-06", "morning", "08:00:00")
         };
 
-        let md = front_page_to_markdown(&frontpage);
+        let md = front_page_to_markdown(&frontpage, false);
         assert!(md.contains("## Test Article - <small>`example`</small>"));
         assert!(md.contains("`example`"));  // source tag
         assert!(md.contains("**Science & Technology**"));  // category
         assert!(md.contains("tags: `tech, science`"));  // tags
         assert!(md.contains("Test summary"));
         assert!(md.contains("Point 1"));
+        assert!(md.contains("- _Published: 2025-05-06T14:30:00-04:00_"));
+    }
+
+    #[test]
+    fn test_story_from_several_outlets() {
+        let article = |source: &str, title: &str| AwfulNewsArticle {
+            source: Some(source.to_string()),
+            dateOfPublication: "2025-05-06".to_string(),
+            timeOfPublication: "14:30:00".to_string(),
+            title: title.to_string(),
+            category: "Conflict & War".to_string(),
+            summaryOfNewsArticle: format!("{} summary.", title),
+            keyTakeAways: vec![],
+            namedEntities: vec![],
+            importantDates: vec![],
+            importantTimeframes: vec![],
+            tags: vec![],
+            content: None,
+            publishedAt: None,
+            novelty: None,
+        };
+        let frontpage = FrontPage {
+            articles: vec![
+                article("https://lite.cnn.com/a", "Warship sinks"),
+                article("https://www.bbc.com/b", "Old warship sinks early"),
+            ],
+            stories: vec![Story {
+                title: "Old warship sinks early".to_string(),
+                category: "Conflict & War".to_string(),
+                articles: vec![1, 0],
+                outlets: vec!["bbc".to_string(), "cnn".to_string()],
+            }],
+            ..FrontPage::empty("2025-05-06", "evening", "20:30:00")
+        };
+
+        let md = front_page_to_markdown(&frontpage, false);
+        assert!(md.contains("## Old warship sinks early\n\n- <small>covered by 2 outlets: `bbc`, `cnn`</small>"));
+        let bbc = md.find("### Old warship sinks early - <small>`bbc`</small>").unwrap();
+        let cnn = md.find("### Warship sinks - <small>`cnn`</small>").unwrap();
+        assert!(bbc < cnn);
+        assert!(md.contains("#### Summary\n\nWarship sinks summary."));
+        assert_eq!(md.matches("---\n").count(), 1);
+    }
+
+    #[test]
+    fn test_hide_carried_over() {
+        let article = |title: &str, novelty: Novelty| AwfulNewsArticle {
+            source: Some(format!("https://example.com/{}", title)),
+            dateOfPublication: "2025-05-06".to_string(),
+            timeOfPublication: "14:30:00".to_string(),
+            title: title.to_string(),
+            category: "Law & Justice".to_string(),
+            summaryOfNewsArticle: "Summary.".to_string(),
+            keyTakeAways: vec![],
+            namedEntities: vec![],
+            importantDates: vec![],
+            importantTimeframes: vec![],
+            tags: vec![],
+            content: None,
+            publishedAt: None,
+            novelty: Some(novelty),
+        };
+        let frontpage = FrontPage {
+            articles: vec![article("Old", Novelty::CarriedOver), article("Changed", Novelty::Updated)],
+            ..FrontPage::empty("2025-05-06", "afternoon", "12:00:00")
+        };
+
+        let all = front_page_to_markdown(&frontpage, false);
+        assert!(all.contains("## Old - <small>`example`</small>\n\n- [source](https://example.com/Old)"));
+        assert!(all.contains("- <small>carried over from an earlier edition</small>"));
+        assert!(all.contains("- <small>updated since an earlier edition</small>"));
+
+        let fresh = front_page_to_markdown(&frontpage, true);
+        assert!(!fresh.contains("## Old"));
+        assert!(fresh.contains("## Changed"));
     }
 }
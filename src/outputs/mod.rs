@@ -1,3 +1,183 @@
 pub mod indexes;
 pub mod json;
 pub mod markdown;
+
+use crate::models::FrontPage;
+use crate::utils::write_atomic;
+use futures::future::{FutureExt, LocalBoxFuture};
+use std::error::Error;
+use tracing::{error, info, instrument};
+
+/// Somewhere a finished front page is written
+pub trait Output {
+    /// Short name used in logs (e.g. "json")
+    fn name(&self) -> &str;
+
+    fn write<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>>;
+
+    /// Called with the edition so far each time an article finishes; outputs that
+    /// serve as a live feed override this, the rest only get the final `write`
+    fn update<'a>(&'a self, _front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+        futures::future::ready(Ok(())).boxed_local()
+    }
+}
+
+/// The JSON API file, `<dir>/<date>/<time_of_day>.json`, rewritten as each article finishes
+pub struct JsonOutput {
+    dir: String,
+}
+
+impl JsonOutput {
+    pub fn new(dir: impl Into<String>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Output for JsonOutput {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn write<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+        json::write_frontpage(front_page, &self.dir).boxed_local()
+    }
+
+    fn update<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+        self.write(front_page)
+    }
+}
+
+/// The mdBook edition page plus the date TOC, `SUMMARY.md` and `daily_news.md` indexes
+pub struct MarkdownOutput {
+    dir: String,
+    hide_carried_over: bool,
+}
+
+impl MarkdownOutput {
+    pub fn new(dir: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            hide_carried_over: false,
+        }
+    }
+
+    /// Leave stories carried over unchanged from an earlier edition out of the page and TOC
+    pub fn hide_carried_over(mut self, hide: bool) -> Self {
+        self.hide_carried_over = hide;
+        self
+    }
+
+    /// `<date>_<time_of_day>.md`, the edition page's file name inside the book
+    pub fn page_filename(front_page: &FrontPage) -> String {
+        format!("{}.md", front_page.id())
+    }
+
+    /// Write the edition page only; re-rendering replaces it
+    pub async fn write_page(&self, front_page: &FrontPage) -> Result<(), Box<dyn Error>> {
+        let path = format!("{}/{}", self.dir, Self::page_filename(front_page));
+        info!(path = %path, "Writing Markdown");
+        if let Err(e) = tokio::fs::write(&path, markdown::front_page_to_markdown(front_page, self.hide_carried_over)).await {
+            error!(path = %path, error = %e, "Failed writing Markdown");
+            return Err(e.into());
+        }
+        info!(path = %path, "Wrote FrontPage Markdown");
+        Ok(())
+    }
+
+    /// Link the edition page from the date TOC, `SUMMARY.md` and `daily_news.md`.
+    /// Updates every index it can; a failure is logged and reported after the rest are attempted
+    pub async fn update_indexes(&self, front_page: &FrontPage) -> Result<(), Box<dyn Error>> {
+        let markdown_filename = Self::page_filename(front_page);
+        let mut result = Ok(());
+
+        if let Err(e) = indexes::update_date_toc_file(&self.dir, front_page, &markdown_filename, self.hide_carried_over).await {
+            error!(error = %e, "Failed to update date TOC file");
+            result = Err(e);
+        }
+        if let Err(e) = indexes::update_summary_md(&self.dir, front_page, &markdown_filename).await {
+            error!(error = %e, "Failed to update SUMMARY.md");
+            result = Err(e);
+        }
+        if let Err(e) = indexes::update_daily_news_index(&self.dir, front_page, &markdown_filename).await {
+            error!(error = %e, "Failed to update daily_news.md index");
+            result = Err(e);
+        }
+        result
+    }
+
+    /// Regenerate every edition page, date TOC, `SUMMARY.md` and `daily_news.md` from
+    /// `editions` alone, e.g. the whole JSON archive. Running it again on the same
+    /// editions writes the same files; hand-written lines of the two book indexes are kept
+    #[instrument(level = "info", skip_all, fields(dir = %self.dir, editions = editions.len()))]
+    pub async fn rebuild(&self, editions: &[FrontPage]) -> Result<(), Box<dyn Error>> {
+        let dates = indexes::by_date(editions);
+        for (date, day) in &dates {
+            for front_page in day {
+                let path = format!("{}/{}", self.dir, Self::page_filename(front_page));
+                write_atomic(&path, markdown::front_page_to_markdown(front_page, self.hide_carried_over)).await?;
+            }
+            let toc_path = format!("{}/{}.md", self.dir, date);
+            write_atomic(&toc_path, indexes::date_toc(date, day, self.hide_carried_over)).await?;
+        }
+
+        let summary_path = format!("{}/SUMMARY.md", self.dir);
+        let summary = tokio::fs::read_to_string(&summary_path).await.ok();
+        write_atomic(&summary_path, indexes::summary_md(summary.as_deref(), editions)).await?;
+
+        let index_path = format!("{}/daily_news.md", self.dir);
+        let index = tokio::fs::read_to_string(&index_path).await.ok();
+        write_atomic(&index_path, indexes::daily_news_md(index.as_deref(), editions)).await?;
+
+        info!(dates = dates.len(), "Rebuilt Markdown book");
+        Ok(())
+    }
+
+    /// Writes the page and every index it can, reporting the last failure
+    async fn write_all(&self, front_page: &FrontPage) -> Result<(), Box<dyn Error>> {
+        let page = self.write_page(front_page).await;
+        let indexes = self.update_indexes(front_page).await;
+        page.and(indexes)
+    }
+}
+
+impl Output for MarkdownOutput {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn write<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+        self.write_all(front_page).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rebuild_is_idempotent() {
+        let dir = std::env::temp_dir().join(format!("atn_rebuild_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // An edition already linked the incremental way, plus a hand-written entry
+        std::fs::write(dir.join("SUMMARY.md"), "# Summary\n\n- [Daily News](./daily_news.md)\n    - [2025-05-06](./2025-05-06.md)\n        - [Morning](./2025-05-06_morning.md)\n        - [Morning](./2025-05-06_morning.md)\n- [About](./about.md)").unwrap();
+
+        let edition = |time_of_day, local_time| FrontPage::empty("2025-05-06", time_of_day, local_time);
+        let editions = vec![edition("morning", "07:00:00"), edition("evening", "20:00:00")];
+        let output = MarkdownOutput::new(dir.to_string_lossy());
+
+        let read_all = || {
+            ["SUMMARY.md", "daily_news.md", "2025-05-06.md", "2025-05-06_morning.md", "2025-05-06_evening.md"]
+                .map(|name| std::fs::read_to_string(dir.join(name)).unwrap())
+        };
+        output.rebuild(&editions).await.unwrap();
+        let first = read_all();
+        output.rebuild(&editions).await.unwrap();
+        assert_eq!(read_all(), first);
+
+        assert_eq!(first[0].matches("_morning.md").count(), 1);
+        assert!(first[0].ends_with("- [Evening](./2025-05-06_evening.md)\n- [About](./about.md)"));
+        assert_eq!(first[2].matches("- [Morning]").count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,603 @@
+use crate::analyzer::Analyzer;
+use crate::{clustering, dedup, novelty};
+use crate::editions::Schedule;
+use crate::dedup::canonical_url;
+use crate::journal::{self, Journal, Resumed};
+use crate::models::{AwfulNewsArticle, EditionState, EditionStatus, FrontPage, NewsArticle};
+use crate::outputs::{json, Output};
+use crate::scrapers::{self, Source};
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tracing::{error, info, instrument, warn};
+
+/// Articles analyzed at once by default
+const ANALYZE_CONCURRENCY: usize = 12;
+
+/// What a re-run of an edition does when its articles differ from the earlier run's
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RerunPolicy {
+    /// Only this run's articles remain
+    #[default]
+    Replace,
+    /// The earlier run's articles are kept and this run's are added to them
+    Append,
+}
+
+/// One run from sources to outputs: index and fetch every source, analyze the
+/// articles, then write the resulting `FrontPage` to each output.
+///
+/// ```ignore
+/// let front_page = Pipeline::new(analyzer)
+///     .sources(scrapers::registry(&settings, None))
+///     .output(JsonOutput::new("./json"))
+///     .output(MarkdownOutput::new("./markdown"))
+///     .run()
+///     .await;
+/// ```
+pub struct Pipeline {
+    analyzer: Analyzer,
+    sources: Vec<Box<dyn Source>>,
+    outputs: Vec<Box<dyn Output>>,
+    concurrency: usize,
+    journal: Option<PathBuf>,
+    resume: bool,
+    cluster_threshold: Option<f64>,
+    /// JSON archive and number of days of earlier editions to compare against
+    compare_with: Option<(PathBuf, u32)>,
+    /// JSON archive holding earlier runs of the same edition, and what to do with them
+    rerun: Option<(PathBuf, RerunPolicy)>,
+    schedule: Schedule,
+    /// Edition to publish instead of the scheduled one
+    edition: Option<String>,
+}
+
+impl Pipeline {
+    pub fn new(analyzer: Analyzer) -> Self {
+        Self {
+            analyzer,
+            sources: Vec::new(),
+            outputs: Vec::new(),
+            concurrency: ANALYZE_CONCURRENCY,
+            journal: None,
+            resume: false,
+            cluster_threshold: Some(clustering::DEFAULT_THRESHOLD),
+            compare_with: None,
+            rerun: None,
+            schedule: Schedule::default(),
+            edition: None,
+        }
+    }
+
+    pub fn source(mut self, source: Box<dyn Source>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    pub fn sources(mut self, sources: impl IntoIterator<Item = Box<dyn Source>>) -> Self {
+        self.sources.extend(sources);
+        self
+    }
+
+    pub fn output(mut self, output: impl Output + 'static) -> Self {
+        self.outputs.push(Box::new(output));
+        self
+    }
+
+    /// How many articles are analyzed at once
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Similarity at which articles are grouped into one story; `None` leaves every article on its own
+    pub fn clustering(mut self, threshold: Option<f64>) -> Self {
+        self.cluster_threshold = threshold;
+        self
+    }
+
+    /// Mark articles as new, updated or carried over compared to the editions written under
+    /// `json_output_dir` in the last `lookback_days` days
+    pub fn compare_with(mut self, json_output_dir: impl Into<PathBuf>, lookback_days: u32) -> Self {
+        self.compare_with = Some((json_output_dir.into(), lookback_days));
+        self
+    }
+
+    /// Look for an earlier run of the same edition under `json_output_dir` and, if its
+    /// articles differ, keep or drop them according to `policy`
+    pub fn on_rerun(mut self, json_output_dir: impl Into<PathBuf>, policy: RerunPolicy) -> Self {
+        self.rerun = Some((json_output_dir.into(), policy));
+        self
+    }
+
+    /// Editions and clock used to name and stamp a new edition
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Publish `name` instead of the edition scheduled for the current time
+    pub fn edition(mut self, name: Option<String>) -> Self {
+        self.edition = name;
+        self
+    }
+
+    /// Append every finished analysis to an NDJSON journal at `path`, removed once the
+    /// edition has been written
+    pub fn journal(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    /// Finish the edition left in the journal, if there is one, instead of starting a new one
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Index and fetch every source in order; a source that fails to index is skipped
+    pub async fn fetch(&self) -> Vec<NewsArticle> {
+        scrapers::fetch_all(&self.sources).await
+    }
+
+    /// Analyze articles concurrently into `front_page`, dropping the ones the model could
+    /// not handle. Each finished analysis is recorded in `journal` and the edition so far is
+    /// passed to every output's `update`. Once all are done the status is `complete`, an
+    /// earlier run of the same edition is reconciled, the articles are compared with earlier
+    /// editions and clustered into stories.
+    #[instrument(level = "info", skip_all)]
+    async fn analyze_into(
+        &self,
+        front_page: &mut FrontPage,
+        articles: &[NewsArticle],
+        earlier_run: Option<FrontPage>,
+        mut journal: Option<&mut Journal>,
+    ) {
+        info!(parallel_batch_size = self.concurrency, "Starting parallel article processing");
+        let already_done = front_page.articles.len();
+        front_page.status = EditionStatus {
+            state: EditionState::InProgress,
+            processed: already_done,
+            total: already_done + articles.len(),
+        };
+        self.update(front_page).await;
+
+        let mut results = stream::iter(articles.iter().enumerate())
+            .map(|(i, article)| self.analyzer.analyze(i, article))
+            .buffer_unordered(self.concurrency);
+        while let Some(result) = results.next().await {
+            front_page.status.processed += 1;
+            if let Some(article) = result {
+                if let Some(journal) = journal.as_deref_mut()
+                    && let Err(e) = journal.record(&article)
+                {
+                    warn!(error = %e, "Failed to append analysis to run journal");
+                }
+                front_page.articles.push(article);
+            }
+            info!(processed = front_page.status.processed, total = front_page.status.total, "Processed article");
+            self.update(front_page).await;
+        }
+        let successful = front_page.articles.len() - already_done;
+        info!(
+            total = articles.len(),
+            successful,
+            failed = articles.len() - successful,
+            "Completed parallel article processing"
+        );
+
+        front_page.status.state = EditionState::Complete;
+        if let (Some(earlier), Some((_, policy))) = (earlier_run, &self.rerun) {
+            reconcile_rerun(front_page, earlier, *policy);
+        }
+        if let Some((dir, lookback_days)) = &self.compare_with {
+            let previous = novelty::load_previous(dir, front_page, *lookback_days).await;
+            novelty::mark(front_page, &previous);
+        }
+        if let Some(threshold) = self.cluster_threshold {
+            front_page.stories = clustering::cluster(&front_page.articles, threshold);
+        }
+    }
+
+    /// Analyze `articles` into a new edition named by the schedule (or `edition`) and
+    /// stamped with the current time, after dropping duplicates so the same text is never
+    /// summarized twice
+    pub async fn analyze_edition(&self, articles: Vec<NewsArticle>) -> FrontPage {
+        let articles = dedup::dedupe(articles);
+        let articles = articles.as_slice();
+        let mut front_page = self.schedule.front_page(self.edition.as_deref());
+        info!(edition = %front_page.time_of_day, local_date = %front_page.local_date, published_at = ?front_page.published_at, "FrontPage initialized");
+
+        // Read before the first update overwrites it, and journaled in case this run crashes
+        let earlier_run = self.earlier_run(&front_page).await;
+        let mut journal = self.journal.as_ref().and_then(|path| {
+            Journal::create(path, &front_page, articles, earlier_run.as_ref())
+                .inspect_err(|e| warn!(path = %path.display(), error = %e, "Could not start run journal; continuing without"))
+                .ok()
+        });
+        self.analyze_into(&mut front_page, articles, earlier_run, journal.as_mut()).await;
+        front_page
+    }
+
+    /// When resuming, analyze whatever the journaled edition is still missing and return it;
+    /// `None` if there is nothing to resume
+    pub async fn resume_edition(&self) -> Option<FrontPage> {
+        if !self.resume {
+            return None;
+        }
+        let path = self.journal.as_ref()?;
+        let Resumed {
+            mut front_page,
+            pending,
+            earlier_run,
+        } = match journal::load(path) {
+            Ok(Some(resumed)) => resumed,
+            Ok(None) => {
+                warn!(path = %path.display(), "No run journal to resume; starting a new edition");
+                return None;
+            }
+            Err(e) => {
+                error!(path = %path.display(), error = %e, "Could not read run journal; starting a new edition");
+                return None;
+            }
+        };
+        info!(
+            local_date = %front_page.local_date,
+            time_of_day = %front_page.time_of_day,
+            done = front_page.articles.len(),
+            remaining = pending.len(),
+            "Resuming interrupted edition"
+        );
+
+        let mut journal = Journal::open(path)
+            .inspect_err(|e| warn!(path = %path.display(), error = %e, "Could not reopen run journal; continuing without"))
+            .ok();
+        self.analyze_into(&mut front_page, &pending, earlier_run, journal.as_mut()).await;
+        Some(front_page)
+    }
+
+    /// The completed run of `front_page`'s edition in the JSON archive, when re-runs are reconciled
+    async fn earlier_run(&self, front_page: &FrontPage) -> Option<FrontPage> {
+        let (dir, _) = self.rerun.as_ref()?;
+        json::read_edition(dir, &front_page.id())
+            .await
+            .filter(|earlier| earlier.status.state == EditionState::Complete)
+    }
+
+    /// Remove the journal once its edition has been written
+    pub fn clear_journal(&self) {
+        if let Some(path) = &self.journal
+            && let Err(e) = journal::remove(path)
+        {
+            warn!(path = %path.display(), error = %e, "Failed to remove run journal");
+        }
+    }
+
+    /// Pass an unfinished front page to every output's `update`; failures are logged
+    async fn update(&self, front_page: &FrontPage) {
+        for output in &self.outputs {
+            if let Err(e) = output.update(front_page).await {
+                warn!(output = output.name(), error = %e, "Failed to update output");
+            }
+        }
+    }
+
+    /// Write the front page to every output; failures are logged and the rest still run
+    #[instrument(level = "info", skip_all)]
+    pub async fn write(&self, front_page: &FrontPage) {
+        for output in &self.outputs {
+            if let Err(e) = output.write(front_page).await {
+                error!(output = output.name(), error = %e, "Failed to write output");
+            }
+        }
+    }
+
+    /// Fetch, analyze and write one edition stamped with the current time, or finish
+    /// the interrupted one when resuming
+    pub async fn run(&self) -> FrontPage {
+        let front_page = match self.resume_edition().await {
+            Some(front_page) => front_page,
+            None => {
+                let articles = self.fetch().await;
+                self.analyze_edition(articles).await
+            }
+        };
+
+        self.write(&front_page).await;
+        self.clear_journal();
+        front_page
+    }
+}
+
+/// Apply `policy` to an edition run again after `earlier` was written. Articles are
+/// matched by canonical URL; one in both runs is always taken from this run.
+fn reconcile_rerun(front_page: &mut FrontPage, earlier: FrontPage, policy: RerunPolicy) {
+    let urls = |articles: &[AwfulNewsArticle]| -> HashSet<String> {
+        articles.iter().filter_map(|a| a.source.as_deref()).map(canonical_url).collect()
+    };
+    let current = urls(&front_page.articles);
+    let before = urls(&earlier.articles);
+    let added = current.difference(&before).count();
+    let only_earlier: Vec<_> = earlier
+        .articles
+        .into_iter()
+        .filter(|a| a.source.as_deref().is_none_or(|s| !current.contains(&canonical_url(s))))
+        .collect();
+    if added == 0 && only_earlier.is_empty() {
+        info!(edition = %front_page.id(), "Re-run has the same articles as the earlier run");
+        return;
+    }
+
+    match policy {
+        RerunPolicy::Replace => {
+            warn!(edition = %front_page.id(), added, dropped = only_earlier.len(), "Re-run's articles differ from the earlier run; replacing them");
+        }
+        RerunPolicy::Append => {
+            info!(edition = %front_page.id(), added, kept = only_earlier.len(), "Re-run's articles differ from the earlier run; appending to them");
+            let this_run = std::mem::replace(&mut front_page.articles, only_earlier);
+            front_page.articles.extend(this_run);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{news_parser_template, MockLlm, Reply};
+    use crate::outputs::{JsonOutput, MarkdownOutput};
+    use crate::scrapers::IndexedArticle;
+    use futures::future::{FutureExt, LocalBoxFuture};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::error::Error;
+
+    /// A source serving canned articles without touching the network
+    struct StaticSource {
+        articles: Vec<(&'static str, &'static str)>,
+    }
+
+    impl Source for StaticSource {
+        fn id(&self) -> &str {
+            "static"
+        }
+
+        fn name(&self) -> &str {
+            "Static"
+        }
+
+        fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+            let indexed = self.articles.iter().map(|(url, _)| IndexedArticle::from(url.to_string())).collect();
+            async move { Ok(indexed) }.boxed_local()
+        }
+
+        fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+            let fetched = articles
+                .into_iter()
+                .filter_map(|a| {
+                    let (_, content) = self.articles.iter().find(|(url, _)| *url == a.url)?;
+                    Some(NewsArticle {
+                        source: a.url,
+                        content: content.to_string(),
+                        published_at: None,
+                    })
+                })
+                .collect();
+            async move { fetched }.boxed_local()
+        }
+    }
+
+    /// An output that remembers the status of every update and write it receives
+    #[derive(Default)]
+    struct StatusLog {
+        updates: RefCell<Vec<EditionStatus>>,
+        written: RefCell<Option<EditionStatus>>,
+    }
+
+    impl Output for Rc<StatusLog> {
+        fn name(&self) -> &str {
+            "status_log"
+        }
+
+        fn write<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+            *self.written.borrow_mut() = Some(front_page.status.clone());
+            async { Ok(()) }.boxed_local()
+        }
+
+        fn update<'a>(&'a self, front_page: &'a FrontPage) -> LocalBoxFuture<'a, Result<(), Box<dyn Error>>> {
+            self.updates.borrow_mut().push(front_page.status.clone());
+            async { Ok(()) }.boxed_local()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_end_to_end() {
+        let dir = std::env::temp_dir().join(format!("atn_pipeline_{}", std::process::id()));
+        let (json_dir, md_dir) = (dir.join("json"), dir.join("markdown"));
+        std::fs::create_dir_all(&md_dir).unwrap();
+
+        // The first request fails with a server error and is retried
+        let mock = MockLlm::echo_articles().await.script([Reply::status(500)]);
+        let source = StaticSource {
+            articles: vec![
+                ("https://example.com/a", "Title: First story\n\nBody of the first story."),
+                ("https://example.com/b", "Title: Second story\n\nBody of the second story."),
+            ],
+        };
+
+        let front_page = Pipeline::new(Analyzer::new(mock.config(), news_parser_template()))
+            .source(Box::new(source))
+            .output(JsonOutput::new(json_dir.to_string_lossy()))
+            .output(MarkdownOutput::new(md_dir.to_string_lossy()))
+            .concurrency(1)
+            .run()
+            .await;
+
+        let mut titles: Vec<&str> = front_page.articles.iter().map(|a| a.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["First story", "Second story"]);
+
+        let json_path = json_dir
+            .join(&front_page.local_date)
+            .join(format!("{}.json", front_page.time_of_day));
+        let written: FrontPage = serde_json::from_slice(&std::fs::read(json_path).unwrap()).unwrap();
+        assert_eq!(written.articles.len(), 2);
+
+        let md_path = md_dir.join(format!("{}_{}.md", front_page.local_date, front_page.time_of_day));
+        assert!(std::fs::read_to_string(md_path).unwrap().contains("First story"));
+        assert!(md_dir.join("SUMMARY.md").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_analyzes_only_missing_articles() {
+        let path = std::env::temp_dir().join(format!("atn_pipeline_journal_{}.ndjson", std::process::id()));
+        let fetched = vec![
+            NewsArticle {
+                source: "https://example.com/a".to_string(),
+                content: "Title: Done before the crash\n\nBody.".to_string(),
+                published_at: None,
+            },
+            NewsArticle {
+                source: "https://example.com/b".to_string(),
+                content: "Title: Still missing\n\nBody.".to_string(),
+                published_at: None,
+            },
+        ];
+        let stamp = FrontPage::empty("2025-05-06", "morning", "07:00:00");
+        let mut done: AwfulNewsArticle = serde_json::from_value(crate::mock_llm::article_json(&fetched[0].content)).unwrap();
+        done.source = Some(fetched[0].source.clone());
+        Journal::create(&path, &stamp, &fetched, None).unwrap().record(&done).unwrap();
+
+        let mock = MockLlm::echo_articles().await;
+        // Nothing is fetched when resuming
+        let source = StaticSource { articles: vec![] };
+        let front_page = Pipeline::new(Analyzer::new(mock.config(), news_parser_template()))
+            .source(Box::new(source))
+            .journal(&path)
+            .resume(true)
+            .run()
+            .await;
+
+        assert_eq!(front_page.local_date, "2025-05-06");
+        assert_eq!(front_page.time_of_day, "morning");
+        let titles: Vec<&str> = front_page.articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Done before the crash", "Still missing"]);
+        assert_eq!(mock.requests().len(), 1);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_resumed_append_keeps_journaled_earlier_run() {
+        let dir = std::env::temp_dir().join(format!("atn_pipeline_rerun_{}", std::process::id()));
+        let path = dir.join("journal.ndjson");
+        let json_dir = dir.join("json");
+        let analysis = |source: &str, title: &str| {
+            let mut a: AwfulNewsArticle = serde_json::from_value(crate::mock_llm::article_json(&format!("Title: {}", title))).unwrap();
+            a.source = Some(source.to_string());
+            a
+        };
+        let edition = |state, articles: Vec<AwfulNewsArticle>| FrontPage {
+            status: EditionStatus {
+                state,
+                processed: articles.len(),
+                total: articles.len(),
+            },
+            articles,
+            ..FrontPage::empty("2025-05-06", "evening", "20:00:00")
+        };
+        let earlier = edition(EditionState::Complete, vec![analysis("https://example.com/a", "From the first run")]);
+        let fetched = vec![NewsArticle {
+            source: "https://example.com/b".to_string(),
+            content: "Title: From the re-run\n\nBody.".to_string(),
+            published_at: None,
+        }];
+        Journal::create(&path, &edition(EditionState::InProgress, vec![]), &fetched, Some(&earlier)).unwrap();
+        // The --append re-run's first update overwrote the earlier run before it crashed
+        let overwritten = edition(EditionState::InProgress, vec![]);
+        JsonOutput::new(json_dir.to_string_lossy()).update(&overwritten).await.unwrap();
+
+        let mock = MockLlm::echo_articles().await;
+        let front_page = Pipeline::new(Analyzer::new(mock.config(), news_parser_template()))
+            .journal(&path)
+            .resume(true)
+            .on_rerun(&json_dir, RerunPolicy::Append)
+            .run()
+            .await;
+
+        let titles: Vec<&str> = front_page.articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["From the first run", "From the re-run"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_outputs_are_updated_as_articles_finish() {
+        // The second article never produces usable JSON but still counts as processed
+        let mock = MockLlm::start(|req| {
+            if req.user().contains("Broken") {
+                Reply::content("not json")
+            } else {
+                Reply::content(crate::mock_llm::article_json(req.user()).to_string())
+            }
+        })
+        .await;
+        let source = StaticSource {
+            articles: vec![
+                ("https://example.com/a", "Title: Fine\n\nBody."),
+                ("https://example.com/b", "Title: Broken\n\nBody."),
+            ],
+        };
+        let log = Rc::new(StatusLog::default());
+
+        let front_page = Pipeline::new(Analyzer::new(mock.config(), news_parser_template()).with_max_reasks(0))
+            .source(Box::new(source))
+            .output(log.clone())
+            .concurrency(1)
+            .run()
+            .await;
+
+        let in_progress = |processed| EditionStatus {
+            state: EditionState::InProgress,
+            processed,
+            total: 2,
+        };
+        assert_eq!(*log.updates.borrow(), vec![in_progress(0), in_progress(1), in_progress(2)]);
+        let complete = EditionStatus {
+            state: EditionState::Complete,
+            processed: 2,
+            total: 2,
+        };
+        assert_eq!(*log.written.borrow(), Some(complete.clone()));
+        assert_eq!(front_page.status, complete);
+        assert_eq!(front_page.articles.len(), 1);
+    }
+
+    #[test]
+    fn test_reconcile_rerun() {
+        let article = |source: &str, title: &str| {
+            let mut a: AwfulNewsArticle = serde_json::from_value(crate::mock_llm::article_json(&format!("Title: {}", title))).unwrap();
+            a.source = Some(source.to_string());
+            a
+        };
+        let edition = |articles| FrontPage {
+            articles,
+            ..FrontPage::empty("2025-05-06", "evening", "20:00:00")
+        };
+        let earlier = || edition(vec![article("https://example.com/a", "Gone"), article("https://example.com/b", "Old take")]);
+        let rerun = || edition(vec![article("https://www.example.com/b/", "New take"), article("https://example.com/c", "Fresh")]);
+        let titles = |fp: &FrontPage| fp.articles.iter().map(|a| a.title.clone()).collect::<Vec<_>>();
+
+        let mut replaced = rerun();
+        reconcile_rerun(&mut replaced, earlier(), RerunPolicy::Replace);
+        assert_eq!(titles(&replaced), vec!["New take", "Fresh"]);
+
+        let mut appended = rerun();
+        reconcile_rerun(&mut appended, earlier(), RerunPolicy::Append);
+        assert_eq!(titles(&appended), vec!["Gone", "New take", "Fresh"]);
+
+        // Appending again to what was written changes nothing
+        let mut again = rerun();
+        reconcile_rerun(&mut again, appended, RerunPolicy::Append);
+        assert_eq!(titles(&again), vec!["Gone", "New take", "Fresh"]);
+    }
+}
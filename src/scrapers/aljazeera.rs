@@ -1,30 +1,47 @@
+use super::{IndexedArticle, Source};
+use crate::http::{self, FetchOptions};
 use crate::models::NewsArticle;
+use crate::settings::SourceSettings;
+use futures::future::{FutureExt, LocalBoxFuture};
 use futures::stream::{self, StreamExt};
-use once_cell::sync::Lazy;
-use reqwest::{Client, Url};
+use reqwest::Url;
 use scraper::{ElementRef, Html, Selector};
 use std::error::Error;
-use std::time::Duration;
 use tracing::{debug, error, info, instrument, warn};
 
 // --- New: date parsing helpers
+use super::common::{self, meta_content, text_of_first};
 use chrono::{DateTime, FixedOffset};
-use serde::Deserialize;
-
-// (Optional) You can add default headers here if needed; UA + timeouts are already set.
-static CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .user_agent(concat!(
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) ",
-            "AppleWebKit/537.36 (KHTML, like Gecko) ",
-            "Chrome/127.0.0.0 Safari/537.36"
-        ))
-        .timeout(Duration::from_secs(20))
-        .pool_idle_timeout(Duration::from_secs(10))
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .expect("failed to build reqwest client")
-});
+
+/// Al Jazeera source
+pub struct AlJazeera {
+    settings: SourceSettings,
+}
+
+impl AlJazeera {
+    pub fn new(settings: SourceSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Source for AlJazeera {
+    fn id(&self) -> &str {
+        "aljazeera"
+    }
+
+    fn name(&self) -> &str {
+        "Al Jazeera"
+    }
+
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+        async { Ok(index_articles(&self.settings).await?.into_iter().map(IndexedArticle::from).collect()) }
+            .boxed_local()
+    }
+
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+        fetch_articles(articles.into_iter().map(|a| a.url).collect(), &self.settings).boxed_local()
+    }
+}
 
 /// Scrape up to 60 articles total (20 per section)
 const SECTION_URLS: &[&str] = &[
@@ -33,108 +50,112 @@ const SECTION_URLS: &[&str] = &[
     "https://www.aljazeera.com/news/",
 ];
 
-/// Index Al Jazeera articles (top 20 from each section; de-duped)
-#[instrument(level = "info")]
-pub async fn index_articles() -> Result<Vec<String>, Box<dyn Error>> {
-    let mut all = Vec::<String>::new();
+const MAX_PER_SECTION: usize = 20;
+const MAX_ARTICLES: usize = 60;
+const CONCURRENCY: usize = 8;
 
-    for section in SECTION_URLS {
-        let res = CLIENT.get(*section).send().await?;
-        let final_url = res.url().to_string(); // after potential redirects
-        let html = res.text().await?;
-        let document = Html::parse_document(&html);
-
-        // 1) Primary selectors commonly present on AJ list pages
-        //    Example you shared: <a class="u-clickable-card__link article-card__link" href="/news/...">
-        let sel_card_link = Selector::parse(r#"a.u-clickable-card__link.article-card__link[href]"#).unwrap();
-        // Also collect any obvious article-card titles that wrap anchors
-        let sel_title_link = Selector::parse(r#"h3.article-card__title"#).unwrap();
-        // Generic anchor fallback on list cards
-        let sel_any_a = Selector::parse(r#"article a[href], div a[href]"#).unwrap();
-
-        let mut urls = Vec::<String>::new();
-
-        // Prefer explicit clickable-card links
-        harvest_selector(&document, &sel_card_link, &mut urls);
-        if urls.len() < 20 {
-            // Some pages put the <h3> and the link on the same anchor; walk up to <a>
-            for title in document.select(&sel_title_link) {
-                if let Some(parent) = title.parent() {
-                    if let Some(el) = ElementRef::wrap(parent) {
-                        if el.value().name() == "a" {
-                            if let Some(href) = el.value().attr("href") {
-                                if let Some(url) = normalize_aljazeera_link(href) {
-                                    if is_target_vertical(&url) && !urls.contains(&url) {
-                                        urls.push(url);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                if urls.len() >= 20 { break; }
-            }
-        }
-        if urls.len() < 20 {
-            harvest_selector(&document, &sel_any_a, &mut urls);
-        }
+/// Index Al Jazeera articles (top `max_per_section` from each section; de-duped)
+#[instrument(level = "info", skip_all)]
+pub async fn index_articles(settings: &SourceSettings) -> Result<Vec<String>, Box<dyn Error>> {
+    let opts = FetchOptions::from(settings);
+    let per_section = settings.max_per_section_or(MAX_PER_SECTION);
+    let max_articles = settings.max_articles_or(MAX_ARTICLES);
+    let mut all = Vec::<String>::new();
 
-        // 2) JSON-LD ItemList fallback (when present)
-        if urls.len() < 20 {
-            let mut from_ld = harvest_itemlist_jsonld(&document);
-            from_ld.retain(|u| is_target_vertical(u));
-            for u in from_ld {
-                if urls.len() >= 20 { break; }
-                if !urls.contains(&u) {
-                    urls.push(u);
-                }
-            }
-        }
-
-        // 3) Regex fallback for article-shaped hrefs
-        if urls.len() < 20 {
-            let mut from_regex = harvest_regex_fallback(&html);
-            from_regex.retain(|u| is_target_vertical(u));
-            for u in from_regex {
-                if urls.len() >= 20 { break; }
-                if !urls.contains(&u) {
-                    urls.push(u);
-                }
-            }
-        }
+    for section in &settings.section_urls_or(SECTION_URLS) {
+        let page = http::fetcher().get(section, opts).await?;
+        let final_url = page.url.to_string(); // after potential redirects
+        let html = page.body;
+        let urls = parse_index(&html, per_section);
 
         if urls.is_empty() {
-            dump_section_debug(*section, &document, &html, &final_url);
+            dump_section_debug(section, &html, &final_url);
         }
 
-        info!(section = *section, count = urls.len(), "Indexed Al Jazeera section URLs");
+        info!(section = %section, count = urls.len(), "Indexed Al Jazeera section URLs");
         debug!(?urls, "Section URLs");
         all.extend(urls);
     }
 
     all.sort();
     all.dedup();
-    // Cap at 60 just in case
-    if all.len() > 60 {
-        all.truncate(60);
-    }
+    // Cap the total across sections
+    all.truncate(max_articles);
     info!(total = all.len(), "Total indexed Al Jazeera URLs");
     Ok(all)
 }
 
-fn harvest_selector(document: &Html, sel: &Selector, urls: &mut Vec<String>) {
-    for a in document.select(sel) {
-        if urls.len() >= 20 {
-            break;
-        }
-        if let Some(href) = a.value().attr("href") {
-            if let Some(url) = normalize_aljazeera_link(href) {
-                if is_target_vertical(&url) && !urls.contains(&url) {
-                    urls.push(url);
+/// Article links on a section page: card links, then card titles, then any anchor,
+/// then JSON-LD `ItemList` entries, then article-shaped hrefs in the raw HTML
+pub fn parse_index(html: &str, per_section: usize) -> Vec<String> {
+    let document = Html::parse_document(html);
+
+    // 1) Primary selectors commonly present on AJ list pages
+    //    Example you shared: <a class="u-clickable-card__link article-card__link" href="/news/...">
+    let sel_card_link = Selector::parse(r#"a.u-clickable-card__link.article-card__link[href]"#).unwrap();
+    // Also collect any obvious article-card titles that wrap anchors
+    let sel_title_link = Selector::parse(r#"h3.article-card__title"#).unwrap();
+    // Generic anchor fallback on list cards
+    let sel_any_a = Selector::parse(r#"article a[href], div a[href]"#).unwrap();
+
+    let mut urls = Vec::<String>::new();
+
+    // Prefer explicit clickable-card links
+    harvest_selector(&document, &sel_card_link, &mut urls, per_section);
+    if urls.len() < per_section {
+        // Some pages put the <h3> and the link on the same anchor; walk up to <a>
+        for title in document.select(&sel_title_link) {
+            if let Some(parent) = title.parent() {
+                if let Some(el) = ElementRef::wrap(parent) {
+                    if el.value().name() == "a" {
+                        if let Some(href) = el.value().attr("href") {
+                            if let Some(url) = normalize_aljazeera_link(href) {
+                                if is_target_vertical(&url) && !urls.contains(&url) {
+                                    urls.push(url);
+                                }
+                            }
+                        }
+                    }
                 }
             }
+            if urls.len() >= per_section { break; }
         }
     }
+    if urls.len() < per_section {
+        harvest_selector(&document, &sel_any_a, &mut urls, per_section);
+    }
+
+    // 2) JSON-LD ItemList fallback (when present)
+    if urls.len() < per_section {
+        let mut from_ld = harvest_itemlist_jsonld(&document);
+        from_ld.retain(|u| is_target_vertical(u));
+        for u in from_ld {
+            if urls.len() >= per_section { break; }
+            if !urls.contains(&u) {
+                urls.push(u);
+            }
+        }
+    }
+
+    // 3) Regex fallback for article-shaped hrefs
+    if urls.len() < per_section {
+        let mut from_regex = harvest_regex_fallback(html);
+        from_regex.retain(|u| is_target_vertical(u));
+        for u in from_regex {
+            if urls.len() >= per_section { break; }
+            if !urls.contains(&u) {
+                urls.push(u);
+            }
+        }
+    }
+
+    urls
+}
+
+fn harvest_selector(document: &Html, sel: &Selector, urls: &mut Vec<String>, limit: usize) {
+    common::harvest_selector(document, sel, urls, limit, |href| {
+        normalize_aljazeera_link(href).filter(|url| is_target_vertical(url))
+    })
 }
 
 /// Parse <script type="application/ld+json"> blocks for ItemList / list pages
@@ -233,12 +254,13 @@ fn normalize_aljazeera_link(href: &str) -> Option<String> {
 
 /// Fetch all Al Jazeera articles concurrently
 #[instrument(level = "info", skip_all)]
-pub async fn fetch_articles(urls: Vec<String>) -> Vec<NewsArticle> {
-    let concurrency = 8usize;
+pub async fn fetch_articles(urls: Vec<String>, settings: &SourceSettings) -> Vec<NewsArticle> {
+    let opts = FetchOptions::from(settings);
+    let concurrency = settings.concurrency_or(CONCURRENCY);
 
     let articles: Vec<NewsArticle> = stream::iter(urls.into_iter())
         .map(|url| async move {
-            let res = fetch_article(&url).await;
+            let res = fetch_article(&url, opts).await;
             (url, res)
         })
         .buffer_unordered(concurrency)
@@ -267,7 +289,7 @@ pub async fn fetch_articles(urls: Vec<String>) -> Vec<NewsArticle> {
 
 /// Fetch a single Al Jazeera article
 #[instrument(level = "info", skip_all, fields(%url))]
-async fn fetch_article(url: &str) -> Result<Option<NewsArticle>, Box<dyn Error>> {
+async fn fetch_article(url: &str, opts: FetchOptions) -> Result<Option<NewsArticle>, Box<dyn Error>> {
     // Basic sanity: only fetch aljazeera.com pages and prefer canonical article URLs
     let parsed = Url::parse(url)?;
     if parsed.domain().unwrap_or_default() != "www.aljazeera.com" {
@@ -275,8 +297,21 @@ async fn fetch_article(url: &str) -> Result<Option<NewsArticle>, Box<dyn Error>>
         return Ok(None);
     }
 
-    let body = CLIENT.get(url).send().await?.text().await?;
-    let document = Html::parse_document(&body);
+    let body = http::fetcher().get_text(url, opts).await?;
+    let article = parse_article(url, &body);
+
+    if article.is_none() {
+        debug!(
+            preview = %body.chars().take(600).collect::<String>().replace('\n', " "),
+            "No article content parsed; HTML preview"
+        );
+    }
+    Ok(article)
+}
+
+/// Extract publication date, title and body from an Al Jazeera article page
+pub fn parse_article(url: &str, html: &str) -> Option<NewsArticle> {
+    let document = Html::parse_document(html);
 
     // ----- PUBLISHED AT (robust) -----
     let (published_dt, published_raw, published_src) = extract_published_at(&document);
@@ -343,160 +378,34 @@ async fn fetch_article(url: &str) -> Result<Option<NewsArticle>, Box<dyn Error>>
     let len = content.len();
     info!(bytes = len, "Parsed Al Jazeera article");
 
-    if found && len > 0 {
-        Ok(Some(NewsArticle {
-            source: url.to_string(),
-            content,
-        }))
-    } else {
-        debug!(
-            preview = %body.chars().take(600).collect::<String>().replace('\n', " "),
-            "No article content parsed; HTML preview"
-        );
-        Ok(None)
-    }
+    (found && len > 0).then(|| NewsArticle {
+        source: url.to_string(),
+        content,
+        published_at: published_dt,
+    })
 }
 
 /* -------------------- DATE HELPERS -------------------- */
 
-fn looks_like_placeholder(s: &str) -> bool {
-    let t = s.trim();
-    t.contains('[') && t.contains(']')
-}
-
-fn clean(s: &str) -> String {
-    s.split_whitespace().collect::<Vec<_>>().join(" ")
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct LdArticle {
-    #[serde(default)]
-    date_published: Option<String>,
-    #[serde(default)]
-    date_modified: Option<String>,
-}
-
-fn parse_rfc3339(s: &str) -> Option<DateTime<FixedOffset>> {
-    DateTime::parse_from_rfc3339(s).ok()
-}
-
 /// Extract (published_iso, raw_string, source_hint)
 fn extract_published_at(document: &Html) -> (Option<DateTime<FixedOffset>>, Option<String>, &'static str) {
-    // A) JSON-LD blocks (Al Jazeera uses NewsArticle schema frequently)
-    if let Ok(sel) = Selector::parse(r#"script[type="application/ld+json"]"#) {
-        for script in document.select(&sel) {
-            if let Some(js) = script
-                .first_child()
-                .and_then(|n| n.value().as_text())
-                .map(|t| t.to_string())
-            {
-                let txt = js.trim();
-                if let Ok(v) = serde_json::from_str::<serde_json::Value>(txt) {
-                    if let Some((dt, raw)) = scan_jsonld_value(&v) {
-                        let raw_clean = clean(&raw);
-                        if !looks_like_placeholder(&raw_clean) {
-                            if let Some(dt) = parse_rfc3339(&dt) {
-                                return (Some(dt), Some(raw_clean), "jsonld");
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // B) Common meta variants
-    for css in &[
-        r#"meta[property="article:published_time"]"#,
-        r#"meta[itemprop="datePublished"]"#,
-        r#"meta[name="date"]"#,
-        r#"meta[property="og:updated_time"]"#,
-    ] {
-        if let Some((raw, _)) = first_meta(document, css, "content") {
-            let raw = clean(&raw);
-            if !looks_like_placeholder(&raw) {
-                if let Some(dt) = parse_rfc3339(&raw) {
-                    return (Some(dt), Some(raw), css);
-                }
-            }
-        }
-    }
-
-    // C) <time datetime="...">
-    if let Ok(sel) = Selector::parse(r#"time[datetime]"#) {
-        if let Some(t) = document.select(&sel).next() {
-            if let Some(raw) = t.value().attr("datetime").map(|s| clean(s)) {
-                if !looks_like_placeholder(&raw) {
-                    if let Some(dt) = parse_rfc3339(&raw) {
-                        return (Some(dt), Some(raw), "time[datetime]");
-                    }
-                }
-            }
-        }
-    }
-
-    // D) Textual fallbacks (e.g., "Published On 18 Oct 2025")
-    if let Ok(sel) = Selector::parse(".gc__date__date .date-simple, [class*=\"date\"], time") {
-        if let Some(el) = document.select(&sel).next() {
-            let raw = clean(&el.text().collect::<String>());
-            if !looks_like_placeholder(&raw) && !raw.is_empty() {
-                return (None, Some(raw), "textual");
-            }
-        }
-    }
-
-    (None, None, "none")
-}
-
-fn first_meta<'a>(document: &'a Html, css: &str, attr: &str) -> Option<(String, ElementRef<'a>)> {
-    let sel = Selector::parse(css).ok()?;
-    let n = document.select(&sel).next()?;
-    let v = n.value().attr(attr)?.to_string();
-    Some((v, n))
-}
-
-fn scan_jsonld_value(v: &serde_json::Value) -> Option<(String, String)> {
-    match v {
-        serde_json::Value::Array(arr) => {
-            for item in arr {
-                if let Some(p) = pick_date_from_ld(item) {
-                    return Some(p);
-                }
-            }
-            None
-        }
-        _ => pick_date_from_ld(v),
-    }
-}
-
-fn pick_date_from_ld(v: &serde_json::Value) -> Option<(String, String)> {
-    // Prefer Article-ish types but don’t require @type
-    let is_article = v
-        .get("@type")
-        .and_then(|t| t.as_str())
-        .map(|t| matches!(t, "NewsArticle" | "Article" | "Report" | "BlogPosting"))
-        .unwrap_or(true);
-
-    if is_article {
-        if let Some(raw) = v.get("datePublished").and_then(|x| x.as_str()).map(|s| s.to_string()) {
-            return Some((raw.clone(), raw));
-        }
-        if let Some(raw) = v.get("dateModified").and_then(|x| x.as_str()).map(|s| s.to_string()) {
-            return Some((raw.clone(), raw));
-        }
-    }
-    if let Some(obj) = v.get("article") {
-        if let Some(raw) = obj.get("datePublished").and_then(|x| x.as_str()).map(|s| s.to_string()) {
-            return Some((raw.clone(), raw));
-        }
-    }
-    None
+    // Al Jazeera uses NewsArticle JSON-LD frequently; textual fallback covers e.g. "Published On 18 Oct 2025"
+    common::extract_published_at(
+        document,
+        &[
+            r#"meta[property="article:published_time"]"#,
+            r#"meta[itemprop="datePublished"]"#,
+            r#"meta[name="date"]"#,
+            r#"meta[property="og:updated_time"]"#,
+        ],
+        ".gc__date__date .date-simple, [class*=\"date\"], time",
+    )
 }
 
 /* -------------------- DEBUG (optional) -------------------- */
 
-fn dump_section_debug(section: &str, document: &Html, html: &str, final_url: &str) {
+fn dump_section_debug(section: &str, html: &str, final_url: &str) {
+    let document = Html::parse_document(html);
     let any_a = Selector::parse("a[href]").unwrap();
     let card_link = Selector::parse(r#"a.u-clickable-card__link.article-card__link[href]"#).unwrap();
 
@@ -515,17 +424,3 @@ fn dump_section_debug(section: &str, document: &Html, html: &str, final_url: &st
     }
     eprintln!("------------------------------------------\n");
 }
-
-/* -------------------- MISC HELPERS -------------------- */
-
-fn text_of_first(document: &Html, css: &str) -> Option<String> {
-    let sel = Selector::parse(css).ok()?;
-    let n = document.select(&sel).next()?;
-    Some(n.text().collect::<Vec<_>>().join(" ").trim().to_string())
-}
-
-fn meta_content(document: &Html, css: &str, attr: &str) -> Option<String> {
-    let sel = Selector::parse(css).ok()?;
-    let n = document.select(&sel).next()?;
-    n.value().attr(attr).map(|s| s.to_string())
-}
\ No newline at end of file
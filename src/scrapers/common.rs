@@ -0,0 +1,267 @@
+use chrono::{DateTime, FixedOffset};
+use scraper::{ElementRef, Html, Selector};
+
+/* -------------------- LINK HELPERS -------------------- */
+
+/// Collect up to `limit` unique URLs from anchors matching `sel`.
+/// `accept` normalizes an href and returns `None` for links that should be skipped.
+pub fn harvest_selector<F>(document: &Html, sel: &Selector, urls: &mut Vec<String>, limit: usize, accept: F)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    for a in document.select(sel) {
+        if urls.len() >= limit {
+            break;
+        }
+        if let Some(url) = a.value().attr("href").and_then(&accept).filter(|u| !urls.contains(u)) {
+            urls.push(url);
+        }
+    }
+}
+
+/* -------------------- TEXT SANITIZATION HELPERS -------------------- */
+
+/// Extract clean text from an HTML element, excluding script and style tags
+pub fn extract_clean_text(element: &ElementRef) -> String {
+    let script_sel = Selector::parse("script").unwrap();
+    let style_sel = Selector::parse("style").unwrap();
+
+    let mut text_parts = Vec::new();
+
+    for node in element.descendants() {
+        // Collect text nodes, but only if they're not inside script/style tags
+        if let Some(text) = node.value().as_text() {
+            let content = text.trim();
+            if !content.is_empty() {
+                // Check if any ancestor is a script or style tag
+                let mut current = node.parent();
+                let mut in_excluded_tag = false;
+
+                while let Some(ancestor) = current {
+                    if let Some(elem) = ElementRef::wrap(ancestor) {
+                        if script_sel.matches(&elem) || style_sel.matches(&elem) {
+                            in_excluded_tag = true;
+                            break;
+                        }
+                    }
+                    current = ancestor.parent();
+                }
+
+                if !in_excluded_tag {
+                    text_parts.push(content);
+                }
+            }
+        }
+    }
+
+    text_parts.join(" ")
+}
+
+/* -------------------- DATE HELPERS -------------------- */
+
+pub fn looks_like_placeholder(s: &str) -> bool {
+    let t = s.trim();
+    t.contains('[') && t.contains(']')
+}
+
+pub fn clean(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse_rfc3339(s: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(s).ok()
+}
+
+/// Extract (published_iso, raw_string, source_hint).
+///
+/// Tries JSON-LD first, then each `meta_selectors` entry (reading `content`), then
+/// `<time datetime>`, and finally the text of the first `textual_selector` match.
+pub fn extract_published_at<'a>(
+    document: &Html,
+    meta_selectors: &[&'a str],
+    textual_selector: &str,
+) -> (Option<DateTime<FixedOffset>>, Option<String>, &'a str) {
+    // A) JSON-LD blocks
+    if let Ok(sel) = Selector::parse(r#"script[type="application/ld+json"]"#) {
+        for script in document.select(&sel) {
+            if let Some(js) = script
+                .first_child()
+                .and_then(|n| n.value().as_text())
+                .map(|t| t.to_string())
+            {
+                let txt = js.trim();
+                // Try array or single object
+                if let Ok(v) = serde_json::from_str::<serde_json::Value>(txt) {
+                    if let Some((dt, raw)) = scan_jsonld_value(&v) {
+                        let raw_clean = clean(&raw);
+                        if !looks_like_placeholder(&raw_clean) {
+                            if let Some(dt) = parse_rfc3339(&dt) {
+                                return (Some(dt), Some(raw_clean), "jsonld");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // B) Meta properties
+    for css in meta_selectors {
+        if let Some((raw, _)) = first_meta(document, css, "content") {
+            let raw = clean(&raw);
+            if !looks_like_placeholder(&raw) {
+                if let Some(dt) = parse_rfc3339(&raw) {
+                    return (Some(dt), Some(raw), css);
+                }
+            }
+        }
+    }
+
+    // C) <time datetime="...">
+    if let Ok(sel) = Selector::parse(r#"time[datetime]"#) {
+        if let Some(t) = document.select(&sel).next() {
+            if let Some(raw) = t.value().attr("datetime").map(clean) {
+                if !looks_like_placeholder(&raw) {
+                    if let Some(dt) = parse_rfc3339(&raw) {
+                        return (Some(dt), Some(raw), "time[datetime]");
+                    }
+                }
+            }
+        }
+    }
+
+    // D) Textual fallbacks (often placeholders — keep as raw only)
+    if let Ok(sel) = Selector::parse(textual_selector) {
+        if let Some(el) = document.select(&sel).next() {
+            let raw = clean(&el.text().collect::<String>());
+            if !raw.is_empty() && !looks_like_placeholder(&raw) {
+                return (None, Some(raw), "textual");
+            }
+        }
+    }
+
+    (None, None, "none")
+}
+
+pub fn first_meta<'a>(document: &'a Html, css: &str, attr: &str) -> Option<(String, ElementRef<'a>)> {
+    let sel = Selector::parse(css).ok()?;
+    let n = document.select(&sel).next()?;
+    let v = n.value().attr(attr)?.to_string();
+    Some((v, n))
+}
+
+fn scan_jsonld_value(v: &serde_json::Value) -> Option<(String, String)> {
+    match v {
+        serde_json::Value::Array(arr) => {
+            for item in arr {
+                if let Some(p) = pick_date_from_ld(item) {
+                    return Some(p);
+                }
+            }
+            None
+        }
+        _ => pick_date_from_ld(v),
+    }
+}
+
+fn pick_date_from_ld(v: &serde_json::Value) -> Option<(String, String)> {
+    // Prefer Article-ish types but don’t require @type
+    let is_article = v
+        .get("@type")
+        .and_then(|t| t.as_str())
+        .map(|t| matches!(t, "NewsArticle" | "Article" | "Report" | "BlogPosting"))
+        .unwrap_or(true);
+
+    if is_article {
+        if let Some(raw) = v.get("datePublished").and_then(|x| x.as_str()).map(|s| s.to_string()) {
+            return Some((raw.clone(), raw));
+        }
+        if let Some(raw) = v.get("dateModified").and_then(|x| x.as_str()).map(|s| s.to_string()) {
+            return Some((raw.clone(), raw));
+        }
+    }
+    if let Some(obj) = v.get("article") {
+        if let Some(raw) = obj.get("datePublished").and_then(|x| x.as_str()).map(|s| s.to_string()) {
+            return Some((raw.clone(), raw));
+        }
+    }
+    None
+}
+
+/* -------------------- MISC HELPERS -------------------- */
+
+pub fn text_of_first(document: &Html, css: &str) -> Option<String> {
+    let sel = Selector::parse(css).ok()?;
+    let n = document.select(&sel).next()?;
+    Some(n.text().collect::<Vec<_>>().join(" ").trim().to_string())
+}
+
+pub fn meta_content(document: &Html, css: &str, attr: &str) -> Option<String> {
+    let sel = Selector::parse(css).ok()?;
+    let n = document.select(&sel).next()?;
+    n.value().attr(attr).map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harvest_selector_dedupes_and_limits() {
+        let html = Html::parse_document(
+            r#"<a href="/a">A</a><a href="/a">A again</a><a href="/b">B</a><a href="/skip">S</a><a href="/c">C</a>"#,
+        );
+        let sel = Selector::parse("a[href]").unwrap();
+        let mut urls = Vec::new();
+        harvest_selector(&html, &sel, &mut urls, 2, |href| {
+            (href != "/skip").then(|| format!("https://example.com{}", href))
+        });
+        assert_eq!(urls, vec!["https://example.com/a", "https://example.com/b"]);
+    }
+
+    #[test]
+    fn test_extract_clean_text_skips_scripts() {
+        let html = Html::parse_fragment(
+            "<div><p>Hello</p><script>var x = 1;</script><style>p {}</style><p>world</p></div>",
+        );
+        let sel = Selector::parse("div").unwrap();
+        let div = html.select(&sel).next().unwrap();
+        assert_eq!(extract_clean_text(&div), "Hello world");
+    }
+
+    #[test]
+    fn test_extract_published_at_jsonld() {
+        let html = Html::parse_document(
+            r#"<script type="application/ld+json">{"@type":"NewsArticle","datePublished":"2025-05-06T14:30:00Z"}</script>"#,
+        );
+        let (dt, raw, src) = extract_published_at(&html, &[], "time");
+        assert_eq!(dt.unwrap().to_rfc3339(), "2025-05-06T14:30:00+00:00");
+        assert_eq!(raw.as_deref(), Some("2025-05-06T14:30:00Z"));
+        assert_eq!(src, "jsonld");
+    }
+
+    #[test]
+    fn test_extract_published_at_meta_then_textual() {
+        let meta = Html::parse_document(
+            r#"<meta property="article:published_time" content="2025-05-06T10:00:00+02:00">"#,
+        );
+        let (dt, _, src) = extract_published_at(&meta, &[r#"meta[property="article:published_time"]"#], "time");
+        assert!(dt.is_some());
+        assert_eq!(src, r#"meta[property="article:published_time"]"#);
+
+        let textual = Html::parse_document(r#"<span class="date">Published On 18 Oct 2025</span>"#);
+        let (dt, raw, src) = extract_published_at(&textual, &[], ".date");
+        assert!(dt.is_none());
+        assert_eq!(raw.as_deref(), Some("Published On 18 Oct 2025"));
+        assert_eq!(src, "textual");
+    }
+
+    #[test]
+    fn test_placeholder_dates_are_ignored() {
+        let html = Html::parse_document(r#"<time>[date]</time>"#);
+        let (dt, raw, src) = extract_published_at(&html, &[], "time");
+        assert!(dt.is_none());
+        assert!(raw.is_none());
+        assert_eq!(src, "none");
+    }
+}
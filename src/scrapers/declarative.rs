@@ -0,0 +1,341 @@
+use super::common::{self, extract_clean_text};
+use super::{IndexedArticle, Source};
+use crate::http::{self, FetchOptions};
+use crate::models::NewsArticle;
+use crate::settings::DeclarativeSettings;
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::error::Error;
+use tracing::{debug, error, info, instrument, warn};
+
+const MAX_PER_SECTION: usize = 20;
+const CONCURRENCY: usize = 4;
+
+const DEFAULT_TITLE_SELECTORS: &[&str] = &[r#"meta[property="og:title"]"#, "h1"];
+const DEFAULT_BODY_SELECTORS: &[&str] = &["article p", "main p"];
+const DEFAULT_DATE_SELECTORS: &[&str] = &[
+    r#"meta[property="article:published_time"]"#,
+    r#"meta[itemprop="datePublished"]"#,
+    r#"meta[name="date"]"#,
+];
+const DEFAULT_DATE_TEXT_SELECTOR: &str = "time";
+
+/// Source whose index and article parsing is described by CSS selectors in config
+pub struct DeclarativeSource {
+    def: DeclarativeSettings,
+}
+
+impl DeclarativeSource {
+    pub fn new(def: DeclarativeSettings) -> Self {
+        Self { def }
+    }
+}
+
+impl Source for DeclarativeSource {
+    fn id(&self) -> &str {
+        &self.def.id
+    }
+
+    fn name(&self) -> &str {
+        self.def.name.as_deref().unwrap_or(&self.def.id)
+    }
+
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+        async { Ok(index_articles(&self.def).await?.into_iter().map(IndexedArticle::from).collect()) }
+            .boxed_local()
+    }
+
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+        fetch_articles(articles.into_iter().map(|a| a.url).collect(), &self.def).boxed_local()
+    }
+}
+
+/// Index every configured list page (top `max_per_section` links each; de-duped)
+#[instrument(level = "info", skip_all, fields(source = %def.id))]
+pub async fn index_articles(def: &DeclarativeSettings) -> Result<Vec<String>, Box<dyn Error>> {
+    let pattern = compile_pattern(def)?;
+    let per_section = def.source.max_per_section_or(MAX_PER_SECTION);
+    let mut all = Vec::<String>::new();
+
+    for section in &def.index_urls {
+        let page = http::fetcher().get(section, FetchOptions::from(&def.source)).await?;
+        let final_url = page.url; // resolve relative links after redirects
+        let html = page.body;
+
+        let urls = parse_index(def, pattern.as_ref(), &final_url, &html, per_section);
+        info!(section = %section, count = urls.len(), "Indexed section URLs");
+        debug!(?urls, "Section URLs");
+
+        for url in urls {
+            if !all.contains(&url) {
+                all.push(url);
+            }
+        }
+    }
+
+    if let Some(max) = def.source.max_articles {
+        all.truncate(max);
+    }
+    info!(total = all.len(), "Total indexed URLs");
+    Ok(all)
+}
+
+fn compile_pattern(def: &DeclarativeSettings) -> Result<Option<Regex>, Box<dyn Error>> {
+    match &def.url_pattern {
+        Some(p) => Ok(Some(Regex::new(p)?)),
+        None => Ok(None),
+    }
+}
+
+/// Pull article links out of a list page: each `link_selectors` entry in order,
+/// then (when a `url_pattern` is set) any matching href in the raw HTML.
+pub fn parse_index(
+    def: &DeclarativeSettings,
+    pattern: Option<&Regex>,
+    base: &Url,
+    html: &str,
+    limit: usize,
+) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let accept = |href: &str| normalize_link(base, href).filter(|u| pattern.is_none_or(|re| re.is_match(u)));
+
+    let mut urls = Vec::<String>::new();
+    for css in &def.link_selectors {
+        if urls.len() >= limit {
+            break;
+        }
+        match Selector::parse(css) {
+            Ok(sel) => common::harvest_selector(&document, &sel, &mut urls, limit, accept),
+            Err(e) => warn!(selector = %css, error = %e, "Invalid link selector"),
+        }
+    }
+
+    // Regex fallback over every href in the raw HTML
+    if urls.len() < limit && pattern.is_some() {
+        let href_re = Regex::new(r#"href=["']([^"']+)["']"#).unwrap();
+        for cap in href_re.captures_iter(html) {
+            if urls.len() >= limit {
+                break;
+            }
+            if let Some(u) = accept(&cap[1]).filter(|u| !urls.contains(u)) {
+                urls.push(u);
+            }
+        }
+    }
+
+    urls
+}
+
+/// Resolve an href against the page URL, keeping only http(s) links without fragments
+fn normalize_link(base: &Url, href: &str) -> Option<String> {
+    let mut url = base.join(href.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url.to_string())
+}
+
+/// Fetch all articles concurrently
+#[instrument(level = "info", skip_all, fields(source = %def.id))]
+pub async fn fetch_articles(urls: Vec<String>, def: &DeclarativeSettings) -> Vec<NewsArticle> {
+    let concurrency = def.source.concurrency_or(CONCURRENCY);
+
+    let articles: Vec<NewsArticle> = stream::iter(urls)
+        .map(|url| async move {
+            let res = fetch_article(&url, def).await;
+            (url, res)
+        })
+        .buffer_unordered(concurrency)
+        .filter_map(|(url, res)| async move {
+            match res {
+                Ok(Some(article)) => {
+                    debug!(%url, "Fetched article");
+                    Some(article)
+                }
+                Ok(None) => {
+                    warn!(%url, "Fetch produced no content");
+                    None
+                }
+                Err(e) => {
+                    error!(error = %e, %url, "Fetch failed");
+                    None
+                }
+            }
+        })
+        .collect()
+        .await;
+
+    info!(count = articles.len(), "Fetched article contents");
+    articles
+}
+
+/// Fetch a single article
+#[instrument(level = "info", skip_all, fields(%url))]
+async fn fetch_article(url: &str, def: &DeclarativeSettings) -> Result<Option<NewsArticle>, Box<dyn Error>> {
+    let body = http::fetcher().get_text(url, FetchOptions::from(&def.source)).await?;
+    let article = parse_article(def, url, &body);
+
+    if article.is_none() {
+        debug!(
+            preview = %body.chars().take(600).collect::<String>().replace('\n', " "),
+            "No article content parsed; HTML preview"
+        );
+    }
+    Ok(article)
+}
+
+/// Extract title, publication date and body from an article page
+pub fn parse_article(def: &DeclarativeSettings, url: &str, html: &str) -> Option<NewsArticle> {
+    let document = Html::parse_document(html);
+
+    // ----- PUBLISHED AT -----
+    let date_selectors = selectors_or(&def.date_selectors, DEFAULT_DATE_SELECTORS);
+    let date_text = def.date_text_selector.as_deref().unwrap_or(DEFAULT_DATE_TEXT_SELECTOR);
+    let (published_dt, published_raw, published_src) =
+        common::extract_published_at(&document, &date_selectors, date_text);
+    debug!(source = published_src, raw = ?published_raw, "Published-at parsed");
+
+    // ----- TITLE -----
+    let title = selectors_or(&def.title_selectors, DEFAULT_TITLE_SELECTORS)
+        .into_iter()
+        .find_map(|css| {
+            common::meta_content(&document, css, "content")
+                .or_else(|| common::text_of_first(&document, css))
+                .filter(|t| !t.is_empty())
+        })
+        .unwrap_or_default();
+
+    // ----- CONTENT EXTRACTION -----
+    let mut content = String::new();
+    for sel in selectors_or(&def.body_selectors, DEFAULT_BODY_SELECTORS)
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+    {
+        let parts: Vec<String> = document
+            .select(&sel)
+            .map(|node| extract_clean_text(&node))
+            .filter(|text| !text.is_empty())
+            .collect();
+        if !parts.is_empty() {
+            content = parts.join("\n\n");
+            break;
+        }
+    }
+
+    if content.is_empty() {
+        return None;
+    }
+
+    // Prepend Title + Date
+    if !title.is_empty() {
+        content = format!("Title: {}\n\n{}", title, content);
+    }
+    if let Some(dt) = published_dt {
+        content = format!("Published: {}\n\n{}", dt.to_rfc3339(), content);
+    } else if let Some(raw) = published_raw {
+        content = format!("Published(raw): {}\n\n{}", raw, content);
+    }
+
+    info!(bytes = content.len(), "Parsed article");
+    Some(NewsArticle {
+        source: url.to_string(),
+        content,
+        published_at: published_dt,
+    })
+}
+
+fn selectors_or<'a>(configured: &'a [String], default: &[&'a str]) -> Vec<&'a str> {
+    if configured.is_empty() {
+        default.to_vec()
+    } else {
+        configured.iter().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::parse_settings;
+
+    fn definition() -> DeclarativeSettings {
+        let yaml = r#"
+declarative:
+  - id: example
+    name: Example Times
+    index_urls:
+      - https://www.example.com/world
+    link_selectors:
+      - "a.story-link[href]"
+      - "h2 a[href]"
+    url_pattern: "^https://www\\.example\\.com/\\d{4}/"
+    title_selectors:
+      - "h1.headline"
+    body_selectors:
+      - "div.story-body p"
+      - "article p"
+"#;
+        parse_settings(yaml).unwrap().declarative.remove(0)
+    }
+
+    #[test]
+    fn test_parse_index_uses_selectors_then_regex() {
+        let def = definition();
+        let pattern = compile_pattern(&def).unwrap();
+        let base = Url::parse("https://www.example.com/world").unwrap();
+        let html = r##"
+            <a class="story-link" href="/2025/05/06/first#comments">First</a>
+            <a class="story-link" href="/about">About</a>
+            <h2><a href="https://www.example.com/2025/05/06/second">Second</a></h2>
+            <div data-url="x"><span href="/2025/05/06/third"></span></div>
+            <a href="mailto:desk@example.com">Mail</a>
+        "##;
+
+        let urls = parse_index(&def, pattern.as_ref(), &base, html, 20);
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.example.com/2025/05/06/first",
+                "https://www.example.com/2025/05/06/second",
+                "https://www.example.com/2025/05/06/third",
+            ]
+        );
+
+        let capped = parse_index(&def, pattern.as_ref(), &base, html, 1);
+        assert_eq!(capped, vec!["https://www.example.com/2025/05/06/first"]);
+    }
+
+    #[test]
+    fn test_parse_article_with_fallback_body() {
+        let def = definition();
+        let html = r#"<html><head>
+            <meta property="article:published_time" content="2025-05-06T14:30:00Z">
+            </head><body>
+            <h1 class="headline">Example headline</h1>
+            <article><p>Body one.</p><script>track()</script><p>Body two.</p></article>
+            </body></html>"#;
+
+        let article = parse_article(&def, "https://www.example.com/2025/05/06/first", html).unwrap();
+        assert_eq!(article.source, "https://www.example.com/2025/05/06/first");
+        assert_eq!(
+            article.content,
+            "Published: 2025-05-06T14:30:00+00:00\n\nTitle: Example headline\n\nBody one.\n\nBody two."
+        );
+    }
+
+    #[test]
+    fn test_parse_article_without_body() {
+        let def = definition();
+        assert!(parse_article(&def, "https://www.example.com/x", "<h1 class=\"headline\">Only a title</h1>").is_none());
+    }
+
+    #[test]
+    fn test_invalid_url_pattern() {
+        let mut def = definition();
+        def.url_pattern = Some("(".to_string());
+        assert!(compile_pattern(&def).is_err());
+    }
+}
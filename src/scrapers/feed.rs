@@ -0,0 +1,470 @@
+use super::{IndexedArticle, Source};
+use crate::http::{self, FetchOptions};
+use crate::models::NewsArticle;
+use crate::settings::FeedSettings;
+use chrono::{DateTime, FixedOffset};
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::stream::{self, StreamExt};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use scraper::{Html, Selector};
+use std::error::Error;
+use tracing::{debug, error, info, instrument, warn};
+
+const MAX_PER_FEED: usize = 20;
+const CONCURRENCY: usize = 4;
+
+/// Source backed by one or more RSS 2.0 / Atom feeds declared under `feeds:`
+pub struct FeedSource {
+    feed: FeedSettings,
+}
+
+impl FeedSource {
+    pub fn new(feed: FeedSettings) -> Self {
+        Self { feed }
+    }
+}
+
+impl Source for FeedSource {
+    fn id(&self) -> &str {
+        &self.feed.id
+    }
+
+    fn name(&self) -> &str {
+        self.feed.name.as_deref().unwrap_or(&self.feed.id)
+    }
+
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+        index_articles(&self.feed).boxed_local()
+    }
+
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+        fetch_articles(articles, &self.feed).boxed_local()
+    }
+}
+
+/// Index every configured feed URL (top `max_per_section` items per feed; de-duped)
+#[instrument(level = "info", skip_all, fields(feed = %feed.id))]
+pub async fn index_articles(feed: &FeedSettings) -> Result<Vec<IndexedArticle>, Box<dyn Error>> {
+    let per_feed = feed.source.max_per_section_or(MAX_PER_FEED);
+    let mut all = Vec::<IndexedArticle>::new();
+
+    for url in &feed.urls {
+        let xml = match http::fetcher().get_text(url, FetchOptions::from(&feed.source)).await {
+            Ok(xml) => xml,
+            Err(e) => {
+                error!(%url, error = %e, "Feed request failed");
+                continue;
+            }
+        };
+
+        let items = match parse_feed(&xml) {
+            Ok(items) => items,
+            Err(e) => {
+                error!(%url, error = %e, "Feed could not be parsed");
+                continue;
+            }
+        };
+
+        let mut count = 0;
+        for item in items.into_iter().take(per_feed) {
+            if !all.iter().any(|a| a.url == item.url) {
+                all.push(item);
+                count += 1;
+            }
+        }
+        info!(%url, count, "Indexed feed items");
+    }
+
+    if let Some(max) = feed.source.max_articles {
+        all.truncate(max);
+    }
+    info!(total = all.len(), "Total indexed feed items");
+    debug!(urls = ?all.iter().map(|a| &a.url).collect::<Vec<_>>(), "Feed URLs");
+    Ok(all)
+}
+
+/// Parse an RSS 2.0 or Atom document into indexed articles
+pub fn parse_feed(xml: &str) -> Result<Vec<IndexedArticle>, Box<dyn Error>> {
+    let mut reader = Reader::from_str(xml);
+    let mut items = Vec::<IndexedArticle>::new();
+    let mut current: Option<IndexedArticle> = None;
+    let mut text = String::new();
+    let mut in_author = false;
+    let mut author_named = false;
+    // Elements open inside an Atom `type="xhtml"` title, content or summary, counting itself;
+    // their markup is flattened into one text value
+    let mut xhtml_depth = 0usize;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(_) if xhtml_depth > 0 => xhtml_depth += 1,
+            Event::Start(e) => {
+                text.clear();
+                match e.local_name().as_ref() {
+                    b"title" | b"content" | b"summary" if is_xhtml(&e)? => xhtml_depth = 1,
+                    b"item" | b"entry" => current = Some(IndexedArticle::default()),
+                    b"author" => {
+                        in_author = true;
+                        author_named = false;
+                    }
+                    b"link" => {
+                        if let Some(item) = current.as_mut() {
+                            set_atom_link(item, &e)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(_) if xhtml_depth > 0 => {}
+            Event::Empty(e) => {
+                if let (b"link", Some(item)) = (e.local_name().as_ref(), current.as_mut()) {
+                    set_atom_link(item, &e)?;
+                }
+            }
+            Event::Text(t) => text.push_str(&t.decode()?),
+            Event::CData(c) => text.push_str(&c.decode()?),
+            Event::GeneralRef(r) => {
+                if let Some(ch) = r.resolve_char_ref()? {
+                    text.push(ch);
+                } else {
+                    let name = r.decode()?;
+                    match resolve_predefined_entity(&name) {
+                        Some(resolved) => text.push_str(resolved),
+                        None => {
+                            text.push('&');
+                            text.push_str(&name);
+                            text.push(';');
+                        }
+                    }
+                }
+            }
+            Event::End(_) if xhtml_depth > 1 => {
+                xhtml_depth -= 1;
+                text.push(' ');
+            }
+            Event::End(e) => {
+                let value = if xhtml_depth == 1 {
+                    xhtml_depth = 0;
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                } else {
+                    text.trim().to_string()
+                };
+                text.clear();
+                let name = e.local_name();
+
+                if matches!(name.as_ref(), b"item" | b"entry") {
+                    if let Some(item) = current.take().filter(|item| !item.url.is_empty()) {
+                        items.push(item);
+                    }
+                    continue;
+                }
+                let Some(item) = current.as_mut() else { continue };
+
+                match name.as_ref() {
+                    b"title" if item.title.is_none() && !value.is_empty() => {
+                        item.title = Some(value);
+                    }
+                    b"link" if item.url.is_empty() && !value.is_empty() => item.url = value,
+                    b"guid" | b"id" if item.url.is_empty() && value.starts_with("http") => {
+                        item.url = value;
+                    }
+                    b"pubDate" | b"published" if !value.is_empty() => item.published = Some(value),
+                    b"updated" | b"date" if item.published.is_none() && !value.is_empty() => {
+                        item.published = Some(value);
+                    }
+                    b"name" if in_author && !value.is_empty() => {
+                        item.authors.push(value);
+                        author_named = true;
+                    }
+                    b"creator" if !value.is_empty() => item.authors.push(value),
+                    b"author" => {
+                        if !author_named && !value.is_empty() {
+                            item.authors.push(value);
+                        }
+                        in_author = false;
+                    }
+                    b"encoded" | b"content" if !value.is_empty() => item.summary = Some(value),
+                    b"description" | b"summary" if item.summary.is_none() && !value.is_empty() => {
+                        item.summary = Some(value);
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+/// Atom `type="xhtml"` text constructs hold markup rather than escaped text
+fn is_xhtml(e: &quick_xml::events::BytesStart) -> Result<bool, Box<dyn Error>> {
+    Ok(e.try_get_attribute("type")?.is_some_and(|attr| attr.value.as_ref() == b"xhtml"))
+}
+
+/// Atom links carry the URL in `href`; only the alternate (or unlabeled) link is the article
+fn set_atom_link(
+    item: &mut IndexedArticle,
+    e: &quick_xml::events::BytesStart,
+) -> Result<(), Box<dyn Error>> {
+    if !item.url.is_empty() {
+        return Ok(());
+    }
+    let rel = match e.try_get_attribute("rel")? {
+        Some(attr) => attr.unescape_value()?.to_string(),
+        None => "alternate".to_string(),
+    };
+    if rel != "alternate" {
+        return Ok(());
+    }
+    if let Some(href) = e.try_get_attribute("href")? {
+        item.url = href.unescape_value()?.trim().to_string();
+    }
+    Ok(())
+}
+
+/// Parse an RSS (RFC 2822) or Atom (RFC 3339) timestamp
+pub fn parse_feed_date(raw: &str) -> Option<DateTime<FixedOffset>> {
+    let raw = raw.trim();
+    DateTime::parse_from_rfc3339(raw)
+        .or_else(|_| DateTime::parse_from_rfc2822(raw))
+        .ok()
+}
+
+/// Fetch all feed articles concurrently
+#[instrument(level = "info", skip_all, fields(feed = %feed.id))]
+pub async fn fetch_articles(articles: Vec<IndexedArticle>, feed: &FeedSettings) -> Vec<NewsArticle> {
+    let concurrency = feed.source.concurrency_or(CONCURRENCY);
+    let opts = FetchOptions::from(&feed.source);
+
+    let articles: Vec<NewsArticle> = stream::iter(articles)
+        .map(|item| async move {
+            let res = fetch_article(&item, opts).await;
+            (item.url, res)
+        })
+        .buffer_unordered(concurrency)
+        .filter_map(|(url, res)| async move {
+            match res {
+                Ok(Some(article)) => {
+                    debug!(%url, "Fetched feed article");
+                    Some(article)
+                }
+                Ok(None) => {
+                    warn!(%url, "Feed fetch produced no content");
+                    None
+                }
+                Err(e) => {
+                    error!(error = %e, %url, "Feed fetch failed");
+                    None
+                }
+            }
+        })
+        .collect()
+        .await;
+
+    info!(count = articles.len(), "Fetched feed article contents");
+    articles
+}
+
+/// Fetch a single feed item's page, falling back to the feed's own summary text
+#[instrument(level = "info", skip_all, fields(url = %item.url))]
+async fn fetch_article(item: &IndexedArticle, opts: FetchOptions) -> Result<Option<NewsArticle>, Box<dyn Error>> {
+    let body = match http::fetcher().get_text(&item.url, opts).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!(error = %e, "Article page unavailable; using feed summary");
+            String::new()
+        }
+    };
+
+    let mut content = extract_body(&body);
+    if content.is_empty() {
+        content = item.summary.as_deref().map(strip_html).unwrap_or_default();
+    }
+
+    let len = content.len();
+    info!(bytes = len, "Parsed feed article");
+    if len == 0 {
+        return Ok(None);
+    }
+
+    // Prepend the metadata the feed already gave us
+    if !item.authors.is_empty() {
+        content = format!("Authors: {}\n\n{}", item.authors.join(", "), content);
+    }
+    if let Some(title) = &item.title {
+        content = format!("Title: {}\n\n{}", title, content);
+    }
+    let published_at = item.published.as_deref().and_then(parse_feed_date);
+    if let Some(raw) = &item.published {
+        content = match published_at {
+            Some(dt) => format!("Published: {}\n\n{}", dt.to_rfc3339(), content),
+            None => format!("Published(raw): {}\n\n{}", raw, content),
+        };
+    }
+
+    Ok(Some(NewsArticle {
+        source: item.url.clone(),
+        content,
+        published_at,
+    }))
+}
+
+/// Pull paragraph text from the most specific article container that has any
+fn extract_body(html: &str) -> String {
+    if html.is_empty() {
+        return String::new();
+    }
+    let document = Html::parse_document(html);
+    let candidates = [
+        r#"[itemprop="articleBody"] p"#,
+        r#"article p"#,
+        r#"main p"#,
+    ];
+
+    for sel in candidates.iter().filter_map(|s| Selector::parse(s).ok()) {
+        let parts: Vec<String> = document
+            .select(&sel)
+            .map(|node| node.text().collect::<Vec<_>>().join(" ").trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect();
+        if !parts.is_empty() {
+            return parts.join("\n\n");
+        }
+    }
+    String::new()
+}
+
+/// Feed descriptions are frequently escaped HTML; keep only the text
+fn strip_html(fragment: &str) -> String {
+    let html = Html::parse_fragment(fragment);
+    html.root_element()
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>Example World News</title>
+    <link>https://example.com/world</link>
+    <item>
+      <title>Talks resume in Geneva &amp; Vienna</title>
+      <link>https://example.com/world/talks-resume</link>
+      <pubDate>Tue, 06 May 2025 14:30:00 +0000</pubDate>
+      <dc:creator>Jane Reporter</dc:creator>
+      <description><![CDATA[<p>Negotiators <b>returned</b> to the table.</p>]]></description>
+    </item>
+    <item>
+      <title>Second story</title>
+      <guid isPermaLink="true">https://example.com/world/second</guid>
+      <author>desk@example.com (World Desk)</author>
+    </item>
+    <item>
+      <title>No link at all</title>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Example Science</title>
+  <link href="https://example.org/"/>
+  <entry>
+    <title type="html">Telescope spots &#8220;new&#8221; comet</title>
+    <link rel="self" href="https://example.org/feed/entry/1"/>
+    <link rel="alternate" href="https://example.org/science/comet"/>
+    <id>tag:example.org,2025:1</id>
+    <updated>2025-05-06T09:00:00Z</updated>
+    <published>2025-05-06T08:15:00+02:00</published>
+    <author><name>Sam Astronomer</name></author>
+    <author><name>Alex Observer</name></author>
+    <summary>Astronomers announced a comet.</summary>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss_items() {
+        let items = parse_feed(RSS).unwrap();
+        assert_eq!(items.len(), 2);
+
+        let first = &items[0];
+        assert_eq!(first.url, "https://example.com/world/talks-resume");
+        assert_eq!(first.title.as_deref(), Some("Talks resume in Geneva & Vienna"));
+        assert_eq!(first.published.as_deref(), Some("Tue, 06 May 2025 14:30:00 +0000"));
+        assert_eq!(first.authors, vec!["Jane Reporter"]);
+        assert_eq!(strip_html(first.summary.as_deref().unwrap()), "Negotiators returned to the table.");
+
+        let second = &items[1];
+        assert_eq!(second.url, "https://example.com/world/second");
+        assert_eq!(second.authors, vec!["desk@example.com (World Desk)"]);
+    }
+
+    #[test]
+    fn test_parse_atom_entries() {
+        let items = parse_feed(ATOM).unwrap();
+        assert_eq!(items.len(), 1);
+
+        let entry = &items[0];
+        assert_eq!(entry.url, "https://example.org/science/comet");
+        assert_eq!(entry.title.as_deref(), Some("Telescope spots \u{201c}new\u{201d} comet"));
+        assert_eq!(entry.published.as_deref(), Some("2025-05-06T08:15:00+02:00"));
+        assert_eq!(entry.authors, vec!["Sam Astronomer", "Alex Observer"]);
+        assert_eq!(entry.summary.as_deref(), Some("Astronomers announced a comet."));
+    }
+
+    #[test]
+    fn test_parse_atom_xhtml_content() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <entry>
+    <link href="https://example.org/science/probe"/>
+    <title type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml">Probe <em>lands</em></div></title>
+    <content type="xhtml">
+      <div xmlns="http://www.w3.org/1999/xhtml">
+        <p>The probe touched down.</p>
+        <p>Data is <b>streaming</b> back.</p>
+      </div>
+    </content>
+  </entry>
+</feed>"#;
+        let entry = &parse_feed(xml).unwrap()[0];
+        assert_eq!(entry.summary.as_deref(), Some("The probe touched down. Data is streaming back."));
+        assert_eq!(entry.title.as_deref(), Some("Probe lands"));
+        assert_eq!(entry.url, "https://example.org/science/probe");
+    }
+
+    #[test]
+    fn test_parse_feed_date() {
+        let rss = parse_feed_date("Tue, 06 May 2025 14:30:00 +0000").unwrap();
+        assert_eq!(rss.to_rfc3339(), "2025-05-06T14:30:00+00:00");
+
+        let atom = parse_feed_date("2025-05-06T08:15:00+02:00").unwrap();
+        assert_eq!(atom.to_rfc3339(), "2025-05-06T08:15:00+02:00");
+
+        assert!(parse_feed_date("yesterday").is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_feed() {
+        assert!(parse_feed("<rss><channel><item></channel>").is_err());
+    }
+
+    #[test]
+    fn test_extract_body_prefers_article_paragraphs() {
+        let html = r#"<html><body><nav><p>Menu</p></nav>
+            <article><p>First paragraph.</p><p>Second paragraph.</p></article></body></html>"#;
+        assert_eq!(extract_body(html), "First paragraph.\n\nSecond paragraph.");
+        assert_eq!(extract_body(""), "");
+    }
+}
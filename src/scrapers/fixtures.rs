@@ -0,0 +1,328 @@
+use super::{aljazeera, apnews, bbcnews, cnn, npr, nyt};
+use crate::http::cache::CachedResponse;
+use crate::models::NewsArticle;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Sources that must have at least one index and one article fixture
+const SOURCES: &[&str] = &["cnn", "npr", "apnews", "aljazeera", "bbcnews", "nyt"];
+
+/// Default per-page link limit for index fixtures
+const DEFAULT_LIMIT: usize = 20;
+
+/// Expectations for one saved page, read from the `.yaml` next to it.
+/// Pages whose name starts with `index` go through the source's `parse_index`,
+/// everything else through `parse_article`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Expected {
+    /// Where the page was fetched from (base for relative links, article source)
+    url: String,
+    /// Index pages: the exact links extracted, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// NYT articles: the title the Top Stories API reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_title: Option<String>,
+    /// A content line (after any `Title: ` / `# ` prefix) starts with this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Value of the `Published:` / `Published(raw):` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    contains: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    excludes: Vec<String>,
+    /// The parser should give up on this page
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    empty: bool,
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+/// Every (source, name, page, expectations) under `tests/fixtures/<source>/`
+fn load_fixtures() -> Vec<(String, String, String, Expected)> {
+    let mut fixtures = Vec::new();
+    let mut sources: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("tests/fixtures")
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.is_dir())
+        .collect();
+    sources.sort();
+
+    for source in sources {
+        let source_id = source.file_name().unwrap().to_string_lossy().to_string();
+        let mut pages: Vec<PathBuf> = std::fs::read_dir(&source)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|ext| ext != "yaml"))
+            .collect();
+        pages.sort();
+
+        for page in pages {
+            let name = page.file_stem().unwrap().to_string_lossy().to_string();
+            let yaml = std::fs::read_to_string(page.with_extension("yaml"))
+                .unwrap_or_else(|_| panic!("{}/{}: missing {}.yaml", source_id, name, name));
+            let expected: Expected = serde_yaml::from_str(&yaml)
+                .unwrap_or_else(|e| panic!("{}/{}.yaml: {}", source_id, name, e));
+            let body = std::fs::read_to_string(&page).unwrap();
+            fixtures.push((source_id.clone(), name, body, expected));
+        }
+    }
+    fixtures
+}
+
+fn parse_index(source: &str, body: &str, expected: &Expected) -> Vec<String> {
+    let limit = expected.limit.unwrap_or(DEFAULT_LIMIT);
+    let base = Url::parse(&expected.url).unwrap();
+    match source {
+        "cnn" => cnn::parse_index(&base, body),
+        "npr" => npr::parse_index(&base, body),
+        "apnews" => apnews::parse_index(&base, body, limit),
+        "aljazeera" => aljazeera::parse_index(body, limit),
+        "bbcnews" => bbcnews::parse_index(body, limit),
+        "nyt" => nyt::parse_index(body, limit).unwrap().into_iter().map(|(url, _)| url).collect(),
+        other => panic!("no index parser for fixture source {:?}", other),
+    }
+}
+
+fn parse_article(source: &str, body: &str, expected: &Expected) -> Option<NewsArticle> {
+    let url = expected.url.as_str();
+    match source {
+        "cnn" => Some(cnn::parse_article(url, body)),
+        "npr" => Some(npr::parse_article(url, body)),
+        "apnews" => apnews::parse_article(url, body),
+        "aljazeera" => aljazeera::parse_article(url, body),
+        "bbcnews" => bbcnews::parse_article(url, body),
+        "nyt" => nyt::parse_article(url, expected.api_title.as_deref().unwrap_or_default(), body),
+        other => panic!("no article parser for fixture source {:?}", other),
+    }
+}
+
+/// Everything wrong with `article` compared to `expected`
+fn check_article(article: Option<NewsArticle>, expected: &Expected) -> Vec<String> {
+    let article = match (article, expected.empty) {
+        (None, true) => return vec![],
+        (Some(a), true) => return vec![format!("expected no article, got {:?}", a.content)],
+        (None, false) => return vec!["parser returned no article".to_string()],
+        (Some(a), false) => a,
+    };
+
+    let mut problems = Vec::new();
+    if article.source != expected.url {
+        problems.push(format!("source {:?} != {:?}", article.source, expected.url));
+    }
+    let lines: Vec<&str> = article.content.lines().map(str::trim).collect();
+    if let Some(title) = &expected.title {
+        let found = lines.iter().any(|l| {
+            let l = l.strip_prefix("Title: ").or_else(|| l.strip_prefix("# ")).unwrap_or(l);
+            l.starts_with(title.as_str())
+        });
+        if !found {
+            problems.push(format!("no title line {:?}", title));
+        }
+    }
+    if let Some(published) = &expected.published {
+        let found = lines.iter().any(|l| {
+            l.strip_prefix("Published: ")
+                .or_else(|| l.strip_prefix("Published(raw): "))
+                .is_some_and(|p| p == published)
+        });
+        if !found {
+            problems.push(format!("no published line {:?}", published));
+        }
+    }
+    for text in &expected.contains {
+        if !article.content.contains(text.as_str()) {
+            problems.push(format!("missing {:?}", text));
+        }
+    }
+    for text in &expected.excludes {
+        if article.content.contains(text.as_str()) {
+            problems.push(format!("unexpected {:?}", text));
+        }
+    }
+    if !problems.is_empty() {
+        problems.push(format!("content was:\n{}", article.content));
+    }
+    problems
+}
+
+#[test]
+fn test_scraper_fixtures() {
+    let fixtures = load_fixtures();
+    let mut failures = Vec::new();
+
+    for (source, name, body, expected) in &fixtures {
+        let problems = if name.starts_with("index") {
+            let urls = parse_index(source, body, expected);
+            if urls == expected.urls {
+                vec![]
+            } else {
+                vec![format!("links {:#?}\n!= expected {:#?}", urls, expected.urls)]
+            }
+        } else {
+            check_article(parse_article(source, body, expected), expected)
+        };
+        failures.extend(problems.into_iter().map(|p| format!("{}/{}: {}", source, name, p)));
+    }
+
+    assert!(failures.is_empty(), "fixture failures:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_every_source_has_fixtures() {
+    let fixtures = load_fixtures();
+    for source in SOURCES {
+        let names: Vec<&str> = fixtures.iter().filter(|f| f.0 == *source).map(|f| f.1.as_str()).collect();
+        assert!(names.iter().any(|n| n.starts_with("index")), "{} has no index fixture", source);
+        assert!(names.iter().any(|n| !n.starts_with("index")), "{} has no article fixture", source);
+    }
+}
+
+/// The built-in source a recorded URL belongs to, and whether it is an article page
+fn classify(url: &Url) -> Option<(&'static str, bool)> {
+    let host = url.host_str()?.trim_start_matches("www.");
+    let path = url.path();
+    if path == "/robots.txt" {
+        return None;
+    }
+    let dated = path
+        .split('/')
+        .any(|segment| segment.len() == 4 && segment.starts_with("20") && segment.bytes().all(|b| b.is_ascii_digit()));
+    match host {
+        "lite.cnn.com" => Some(("cnn", dated)),
+        "text.npr.org" => Some(("npr", path.len() > 1)),
+        "apnews.com" => Some(("apnews", path.starts_with("/article/"))),
+        "aljazeera.com" => Some(("aljazeera", dated)),
+        "bbc.com" | "bbc.co.uk" => Some(("bbcnews", path.contains("/articles/"))),
+        "api.nytimes.com" => Some(("nyt", false)),
+        // NYT article pages are fetched through this proxy
+        "accessarticlenow.com" => Some(("nyt", true)),
+        _ => None,
+    }
+}
+
+/// Drop what no parser reads (scripts other than JSON-LD, styles, SVG, comments, inline
+/// styling) so a recorded page is small enough to check in
+fn trim_page(html: &str) -> String {
+    if html.trim_start().starts_with('{') {
+        return html.to_string();
+    }
+    let mut page = html.to_string();
+    for tag in ["script", "style", "svg", "noscript", "iframe", "template"] {
+        let block = Regex::new(&format!(r"(?is)<{0}\b([^>]*)>.*?</{0}\s*>", tag)).unwrap();
+        page = block
+            .replace_all(&page, |c: &Captures| {
+                if tag == "script" && c[1].contains("ld+json") { c[0].to_string() } else { String::new() }
+            })
+            .into_owned();
+    }
+    for noise in [r"(?s)<!--.*?-->", r"(?i)<link\b[^>]*>", r#"(?i)\s(?:style|srcset|sizes)=(?:"[^"]*"|'[^']*')"#] {
+        page = Regex::new(noise).unwrap().replace_all(&page, "").into_owned();
+    }
+    page.lines().map(str::trim_end).filter(|l| !l.trim().is_empty()).collect::<Vec<_>>().join("\n") + "\n"
+}
+
+/// What the parsers extract from a page today, as expectations to review
+fn current_expectations(source: &str, url: &str, body: &str, article: bool) -> Expected {
+    let mut expected = Expected {
+        url: url.to_string(),
+        ..Expected::default()
+    };
+    if !article {
+        expected.urls = parse_index(source, body, &expected);
+        return expected;
+    }
+    let Some(parsed) = parse_article(source, body, &expected) else {
+        expected.empty = true;
+        return expected;
+    };
+    let lines: Vec<&str> = parsed.content.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    expected.title = lines
+        .iter()
+        .find_map(|l| l.strip_prefix("Title: ").or_else(|| l.strip_prefix("# ")))
+        .or_else(|| lines.iter().copied().find(|l| !l.starts_with("Published")))
+        .map(str::to_string);
+    expected.published = lines
+        .iter()
+        .find_map(|l| l.strip_prefix("Published: ").or_else(|| l.strip_prefix("Published(raw): ")))
+        .map(str::to_string);
+    expected.contains = lines.iter().filter(|l| l.len() > 80).take(2).map(|l| l.chars().take(60).collect()).collect();
+    expected
+}
+
+/// Turn a `--record` directory into fixtures named `<index|article>_<date>_<n>`, with
+/// expectations taken from what the parsers extract today. Read the generated `.yaml`
+/// files against the pages before committing them:
+///
+/// `ATN_RECORDING=<dir> cargo test import_recorded_pages -- --ignored`
+#[test]
+#[ignore = "writes tests/fixtures from the --record directory in $ATN_RECORDING"]
+fn import_recorded_pages() {
+    let dir = std::env::var("ATN_RECORDING").expect("set ATN_RECORDING to a --record directory");
+    let mut recorded: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .expect("ATN_RECORDING")
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    recorded.sort();
+
+    for path in recorded {
+        let Ok(entry) = serde_json::from_slice::<CachedResponse>(&std::fs::read(&path).unwrap()) else {
+            continue;
+        };
+        let Some((source, article)) = Url::parse(&entry.url).ok().as_ref().and_then(classify) else {
+            println!("skipped {}", entry.url);
+            continue;
+        };
+        let body = trim_page(&entry.body);
+        let ext = if body.trim_start().starts_with('{') { "json" } else { "html" };
+        let kind = if article { "article" } else { "index" };
+        let date = entry.fetched_at.get(..10).unwrap_or("undated").replace('-', "_");
+        let source_dir = fixtures_dir().join(source);
+        let page = (1..)
+            .map(|n| source_dir.join(format!("{}_{}_{}.{}", kind, date, n, ext)))
+            .find(|p| !p.exists())
+            .unwrap();
+
+        let expected = current_expectations(source, &entry.url, &body, article);
+        let yaml = format!(
+            "# Recorded {}; expectations are what the parser extracted at import\n{}",
+            entry.fetched_at,
+            serde_yaml::to_string(&expected).unwrap()
+        );
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::write(&page, body).unwrap();
+        std::fs::write(page.with_extension("yaml"), yaml).unwrap();
+        println!("{} -> {}", entry.url, page.display());
+    }
+}
+
+#[test]
+fn test_trim_page() {
+    let html = r#"<html><head><script src="a.js"></script><style>p{}</style>
+<script type="application/ld+json">{"datePublished":"2025-05-06"}</script>
+<link rel="stylesheet" href="x.css"></head>
+<body><!-- ad --><p style="color:red" data-testid="para">Text</p>
+
+<svg><path d="M0"/></svg></body></html>"#;
+    assert_eq!(
+        trim_page(html),
+        "<html><head>\n<script type=\"application/ld+json\">{\"datePublished\":\"2025-05-06\"}</script>\n</head>\n<body><p data-testid=\"para\">Text</p>\n</body></html>\n"
+    );
+
+    let classified = |url: &str| classify(&Url::parse(url).unwrap());
+    assert_eq!(classified("https://lite.cnn.com/2025/05/06/world/story"), Some(("cnn", true)));
+    assert_eq!(classified("https://text.npr.org"), Some(("npr", false)));
+    assert_eq!(classified("https://apnews.com/world-news"), Some(("apnews", false)));
+    assert_eq!(classified("https://www.bbc.com/news/articles/c4g7vzpzx1yo"), Some(("bbcnews", true)));
+    assert_eq!(classified("https://text.npr.org/robots.txt"), None);
+}
@@ -4,3 +4,186 @@ pub mod npr;
 pub mod aljazeera;
 pub mod bbcnews;
 pub mod nyt;
+pub mod feed;
+pub mod common;
+pub mod declarative;
+#[cfg(test)]
+mod fixtures;
+
+use crate::models::NewsArticle;
+use crate::settings::Settings;
+use futures::future::LocalBoxFuture;
+use std::error::Error;
+use tracing::{error, info, instrument};
+
+/// A link discovered while indexing a source, plus any metadata the index already knows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexedArticle {
+    pub url: String,
+    pub title: Option<String>,
+    /// Raw publication timestamp as given by the index (e.g. an RSS `pubDate`)
+    pub published: Option<String>,
+    pub authors: Vec<String>,
+    /// Teaser or full text shipped alongside the link (e.g. an RSS `description`)
+    pub summary: Option<String>,
+}
+
+impl From<String> for IndexedArticle {
+    fn from(url: String) -> Self {
+        Self {
+            url,
+            ..Default::default()
+        }
+    }
+}
+
+/// Common contract implemented by every news outlet
+pub trait Source {
+    /// Stable identifier used in logs and configuration (e.g. "cnn")
+    fn id(&self) -> &str;
+
+    /// Human-readable outlet name
+    fn name(&self) -> &str;
+
+    /// Discover the article links currently published by the outlet
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>>;
+
+    /// Fetch and parse the given articles, skipping any that fail
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>>;
+}
+
+/// Build the ordered list of sources scraped on each run, leaving out disabled ones
+pub fn registry(settings: &Settings, nyt_api_key: Option<String>) -> Vec<Box<dyn Source>> {
+    let mut sources: Vec<Box<dyn Source>> = vec![
+        Box::new(cnn::Cnn::new(settings.source("cnn"))),
+        Box::new(npr::Npr::new(settings.source("npr"))),
+        Box::new(apnews::ApNews::new(settings.source("apnews"))),
+        Box::new(aljazeera::AlJazeera::new(settings.source("aljazeera"))),
+        Box::new(bbcnews::BbcNews::new(settings.source("bbcnews"))),
+        Box::new(nyt::Nyt::new(nyt_api_key, settings.source("nyt"))),
+    ];
+    sources.retain(|source| {
+        let enabled = settings.source(source.id()).enabled;
+        if !enabled {
+            info!(source = source.id(), "Source disabled in config");
+        }
+        enabled
+    });
+
+    for def in &settings.declarative {
+        if !def.source.enabled {
+            info!(source = %def.id, "Declarative source disabled in config");
+            continue;
+        }
+        sources.push(Box::new(declarative::DeclarativeSource::new(def.clone())));
+    }
+
+    for feed in &settings.feeds {
+        if !feed.source.enabled {
+            info!(source = %feed.id, "Feed disabled in config");
+            continue;
+        }
+        sources.push(Box::new(feed::FeedSource::new(feed.clone())));
+    }
+
+    sources
+}
+
+/// Index and fetch every source in order; a source that fails to index is skipped
+#[instrument(level = "info", skip_all)]
+pub async fn fetch_all(sources: &[Box<dyn Source>]) -> Vec<NewsArticle> {
+    let mut articles = Vec::new();
+    for source in sources {
+        let indexed = match source.index().await {
+            Ok(indexed) => indexed,
+            Err(e) => {
+                error!(source = source.id(), error = %e, "Indexing failed; skipping source");
+                continue;
+            }
+        };
+        info!(source = source.id(), name = source.name(), count = indexed.len(), "Indexed source");
+        articles.extend(source.fetch(indexed).await);
+    }
+    info!(count = articles.len(), "Total articles to analyze");
+    articles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::settings::parse_settings;
+
+    #[test]
+    fn test_registry_order() {
+        let ids: Vec<String> = registry(&Settings::default(), None)
+            .iter()
+            .map(|s| s.id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["cnn", "npr", "apnews", "aljazeera", "bbcnews", "nyt"]);
+    }
+
+    #[test]
+    fn test_registry_skips_disabled_sources() {
+        let settings = parse_settings("sources:\n  bbcnews:\n    enabled: false\n  nyt:\n    enabled: false\n").unwrap();
+        let ids: Vec<String> = registry(&settings, None)
+            .iter()
+            .map(|s| s.id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["cnn", "npr", "apnews", "aljazeera"]);
+    }
+
+    #[test]
+    fn test_registry_appends_feeds() {
+        let yaml = r#"
+feeds:
+  - id: example_world
+    name: Example World
+    urls:
+      - https://example.com/world/rss
+  - id: example_off
+    enabled: false
+    urls:
+      - https://example.com/off/rss
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        let sources = registry(&settings, None);
+        let last = sources.last().unwrap();
+        assert_eq!(sources.len(), 7);
+        assert_eq!(last.id(), "example_world");
+        assert_eq!(last.name(), "Example World");
+    }
+
+    #[test]
+    fn test_registry_appends_declarative_sources() {
+        let yaml = r#"
+declarative:
+  - id: example
+    index_urls:
+      - https://www.example.com/world
+    link_selectors:
+      - "a[href]"
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        let sources = registry(&settings, None);
+        assert_eq!(sources.len(), 7);
+        assert_eq!(sources[6].id(), "example");
+        assert_eq!(sources[6].name(), "example");
+    }
+
+    #[test]
+    fn test_registry_ids_are_unique() {
+        let sources = registry(&Settings::default(), None);
+        let mut ids: Vec<&str> = sources.iter().map(|s| s.id()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), sources.len());
+    }
+
+    #[test]
+    fn test_indexed_article_from_url() {
+        let article = IndexedArticle::from("https://lite.cnn.com/a".to_string());
+        assert_eq!(article.url, "https://lite.cnn.com/a");
+        assert_eq!(article.title, None);
+    }
+}
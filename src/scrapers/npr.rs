@@ -1,46 +1,87 @@
+use super::{IndexedArticle, Source};
+use crate::http::{self, FetchOptions};
 use crate::models::NewsArticle;
+use crate::settings::SourceSettings;
+use futures::future::{FutureExt, LocalBoxFuture};
 use futures::stream::{self, StreamExt};
-use reqwest::get;
 use scraper::{Html, Selector};
 use std::error::Error;
 use tracing::{debug, error, info, instrument, warn};
 use url::Url;
 
-/// Index NPR Text homepage to extract article URLs
-#[instrument(level = "info")]
-pub async fn index_articles() -> Result<Vec<String>, Box<dyn Error>> {
-    let npr_page_url = "https://text.npr.org";
-    let npr_base_url = Url::parse(npr_page_url)?;
+/// NPR Text source
+pub struct Npr {
+    settings: SourceSettings,
+}
 
-    let html = get(npr_page_url).await?.text().await?;
-    let document = Html::parse_document(&html);
-    let story_selector = Selector::parse(".topic-title").unwrap();
-    
+impl Npr {
+    pub fn new(settings: SourceSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Source for Npr {
+    fn id(&self) -> &str {
+        "npr"
+    }
+
+    fn name(&self) -> &str {
+        "NPR Text"
+    }
+
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+        async { Ok(index_articles(&self.settings).await?.into_iter().map(IndexedArticle::from).collect()) }
+            .boxed_local()
+    }
+
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+        fetch_articles(articles.into_iter().map(|a| a.url).collect(), &self.settings).boxed_local()
+    }
+}
+
+const SECTION_URLS: &[&str] = &["https://text.npr.org"];
+
+/// Index NPR Text homepage to extract article URLs
+#[instrument(level = "info", skip_all)]
+pub async fn index_articles(settings: &SourceSettings) -> Result<Vec<String>, Box<dyn Error>> {
+    let opts = FetchOptions::from(settings);
     let mut article_urls = Vec::new();
-    for element in document.select(&story_selector) {
-        if let Some(href) = element.value().attr("href") {
-            if let Ok(resolved) = npr_base_url.join(href) {
-                article_urls.push(resolved.to_string());
-            }
-        }
+
+    for npr_page_url in settings.section_urls_or(SECTION_URLS) {
+        let npr_base_url = Url::parse(&npr_page_url)?;
+
+        let html = http::fetcher().get_text(&npr_page_url, opts).await?;
+        article_urls.extend(parse_index(&npr_base_url, &html));
+
+        info!(
+            count = article_urls.len(),
+            source = %npr_page_url,
+            "Indexed NPR article URLs"
+        );
+    }
+
+    if let Some(max) = settings.max_articles {
+        article_urls.truncate(max);
     }
-    
-    info!(
-        count = article_urls.len(),
-        source = npr_page_url,
-        "Indexed NPR article URLs"
-    );
     debug!(urls = ?article_urls, "NPR URLs");
-    
+
     Ok(article_urls)
 }
 
-/// Fetch all NPR articles concurrently
+/// Fetch all NPR articles (sequentially unless configured otherwise)
 #[instrument(level = "info", skip_all)]
-pub async fn fetch_articles(urls: Vec<String>) -> Vec<NewsArticle> {
-    let articles: Vec<NewsArticle> = stream::iter(urls.clone())
-        .then(|url: String| async move {
-            match fetch_article(&url).await {
+pub async fn fetch_articles(urls: Vec<String>, settings: &SourceSettings) -> Vec<NewsArticle> {
+    let opts = FetchOptions::from(settings);
+    let concurrency = settings.concurrency_or(1);
+
+    let articles: Vec<NewsArticle> = stream::iter(urls)
+        .map(|url: String| async move {
+            let res = fetch_article(&url, opts).await;
+            (url, res)
+        })
+        .buffered(concurrency)
+        .filter_map(|(url, res)| async move {
+            match res {
                 Ok(Some(article)) => {
                     debug!(%url, "Fetched NPR article");
                     Some(article)
@@ -55,8 +96,6 @@ pub async fn fetch_articles(urls: Vec<String>) -> Vec<NewsArticle> {
                 }
             }
         })
-        .filter(|opt| std::future::ready(opt.is_some()))
-        .map(|opt| opt.unwrap())
         .collect()
         .await;
     
@@ -64,15 +103,35 @@ pub async fn fetch_articles(urls: Vec<String>) -> Vec<NewsArticle> {
     articles
 }
 
+/// Story links on a NPR index page, resolved against `base`
+pub fn parse_index(base: &Url, html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let story_selector = Selector::parse(".topic-title").unwrap();
+
+    let mut article_urls = Vec::new();
+    for element in document.select(&story_selector) {
+        if let Some(href) = element.value().attr("href") {
+            if let Ok(resolved) = base.join(href) {
+                article_urls.push(resolved.to_string());
+            }
+        }
+    }
+    article_urls
+}
+
 /// Fetch a single NPR article
 #[instrument(level = "info", skip_all, fields(%url))]
-async fn fetch_article(url: &str) -> Result<Option<NewsArticle>, Box<dyn Error>> {
-    let body = get(url).await?.text().await?;
-    let document = Html::parse_document(&body);
+async fn fetch_article(url: &str, opts: FetchOptions) -> Result<Option<NewsArticle>, Box<dyn Error>> {
+    let body = http::fetcher().get_text(url, opts).await?;
+    Ok(Some(parse_article(url, &body)))
+}
 
+/// Headline and story text of a NPR article page
+pub fn parse_article(url: &str, html: &str) -> NewsArticle {
+    let document = Html::parse_document(html);
     let mut content = String::new();
-    let headline_selector = Selector::parse(".story-head")?;
-    let article_selector = Selector::parse(".paragraphs-container")?;
+    let headline_selector = Selector::parse(".story-head").unwrap();
+    let article_selector = Selector::parse(".paragraphs-container").unwrap();
 
     for element in document
         .select(&headline_selector)
@@ -85,8 +144,9 @@ async fn fetch_article(url: &str) -> Result<Option<NewsArticle>, Box<dyn Error>>
 
     let len = content.len();
     info!(bytes = len, "Parsed NPR article");
-    Ok(Some(NewsArticle {
+    NewsArticle {
         source: url.to_string(),
         content,
-    }))
+        published_at: None,
+    }
 }
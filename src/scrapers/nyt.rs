@@ -1,27 +1,62 @@
+use super::common;
+use super::{IndexedArticle, Source};
+use crate::http::{self, FetchOptions};
 use crate::models::NewsArticle;
+use crate::settings::SourceSettings;
+use futures::future::{FutureExt, LocalBoxFuture};
 use futures::stream::{self, StreamExt};
-use once_cell::sync::Lazy;
-use reqwest::Client;
 use scraper::{Html, Selector};
 use serde::Deserialize;
 use std::error::Error;
-use std::time::Duration;
 use tracing::{debug, error, info, instrument, warn};
 
-// Global HTTP client with realistic UA + timeouts
-static CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .user_agent(concat!(
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) ",
-            "AppleWebKit/537.36 (KHTML, like Gecko) ",
-            "Chrome/127.0.0.0 Safari/537.36"
-        ))
-        .timeout(Duration::from_secs(30))
-        .pool_idle_timeout(Duration::from_secs(10))
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .expect("failed to build reqwest client")
-});
+/// New York Times source (requires a Top Stories API key)
+pub struct Nyt {
+    api_key: Option<String>,
+    settings: SourceSettings,
+}
+
+impl Nyt {
+    pub fn new(api_key: Option<String>, settings: SourceSettings) -> Self {
+        Self { api_key, settings }
+    }
+}
+
+impl Source for Nyt {
+    fn id(&self) -> &str {
+        "nyt"
+    }
+
+    fn name(&self) -> &str {
+        "The New York Times"
+    }
+
+    fn index(&self) -> LocalBoxFuture<'_, Result<Vec<IndexedArticle>, Box<dyn Error>>> {
+        async {
+            let articles = index_articles(self.api_key.as_deref(), &self.settings).await?;
+            Ok(articles
+                .into_iter()
+                .map(|(url, title)| IndexedArticle {
+                    url,
+                    title: Some(title),
+                    ..Default::default()
+                })
+                .collect())
+        }
+        .boxed_local()
+    }
+
+    fn fetch(&self, articles: Vec<IndexedArticle>) -> LocalBoxFuture<'_, Vec<NewsArticle>> {
+        let articles = articles
+            .into_iter()
+            .map(|a| (a.url, a.title.unwrap_or_default()))
+            .collect();
+        fetch_articles(articles, &self.settings).boxed_local()
+    }
+}
+
+const MAX_ARTICLES: usize = 30;
+const CONCURRENCY: usize = 4; // Lower concurrency to be respectful to removepaywalls.com
 
 #[derive(Debug, Deserialize)]
 struct NYTimesResponse {
@@ -35,8 +70,11 @@ struct NYTimesArticle {
 }
 
 /// Index NYT articles via their Top Stories API
-#[instrument(level = "info")]
-pub async fn index_articles(api_key: Option<&str>) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+#[instrument(level = "info", skip_all)]
+pub async fn index_articles(
+    api_key: Option<&str>,
+    settings: &SourceSettings,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
     let api_key = match api_key {
         Some(key) => key,
         None => {
@@ -52,24 +90,12 @@ pub async fn index_articles(api_key: Option<&str>) -> Result<Vec<(String, String
 
     info!("Fetching NYT top stories from API");
     
-    let response = CLIENT.get(&api_url).send().await?;
-    
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await?;
-        error!(status = %status, body = %body, "NYT API request failed");
-        return Err(format!("NYT API returned status {}: {}", status, body).into());
-    }
+    let body = http::fetcher().get_text(&api_url, FetchOptions::from(settings)).await.map_err(|e| {
+        error!(error = %e, "NYT API request failed");
+        e
+    })?;
 
-    let nyt_response: NYTimesResponse = response.json().await?;
-    
-    // Take first 30 URLs and titles
-    let articles: Vec<(String, String)> = nyt_response
-        .results
-        .into_iter()
-        .take(30)
-        .map(|article| (article.url, article.title))
-        .collect();
+    let articles = parse_index(&body, settings.max_articles_or(MAX_ARTICLES))?;
 
     info!(
         count = articles.len(),
@@ -81,14 +107,29 @@ pub async fn index_articles(api_key: Option<&str>) -> Result<Vec<(String, String
     Ok(articles)
 }
 
+/// The first `limit` (URL, title) pairs of a Top Stories API response
+pub fn parse_index(body: &str, limit: usize) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let nyt_response: NYTimesResponse = serde_json::from_str(body)?;
+    Ok(nyt_response
+        .results
+        .into_iter()
+        .take(limit)
+        .map(|article| (article.url, article.title))
+        .collect())
+}
+
 /// Fetch all NYT articles concurrently through removepaywalls.com
 #[instrument(level = "info", skip_all)]
-pub async fn fetch_articles(articles: Vec<(String, String)>) -> Vec<NewsArticle> {
-    let concurrency = 4usize; // Lower concurrency to be respectful to removepaywalls.com
+pub async fn fetch_articles(
+    articles: Vec<(String, String)>,
+    settings: &SourceSettings,
+) -> Vec<NewsArticle> {
+    let concurrency = settings.concurrency_or(CONCURRENCY);
+    let opts = FetchOptions::from(settings);
 
     let articles: Vec<NewsArticle> = stream::iter(articles.into_iter())
         .map(|(url, api_title)| async move {
-            let res = fetch_article(&url, &api_title).await;
+            let res = fetch_article(&url, &api_title, opts).await;
             (url, res)
         })
         .buffer_unordered(concurrency)
@@ -117,25 +158,40 @@ pub async fn fetch_articles(articles: Vec<(String, String)>) -> Vec<NewsArticle>
 
 /// Fetch a single NYT article through accessarticlenow.com (the iframe backend)
 #[instrument(level = "info", skip_all, fields(%url))]
-async fn fetch_article(url: &str, api_title: &str) -> Result<Option<NewsArticle>, Box<dyn Error>> {
+async fn fetch_article(url: &str, api_title: &str, opts: FetchOptions) -> Result<Option<NewsArticle>, Box<dyn Error>> {
     // Construct the accessarticlenow.com URL (this is what removepaywalls.com uses in its iframe)
     let proxy_url = format!("https://accessarticlenow.com/api/c/google?q={}", url);
     
     info!(%proxy_url, "Fetching through accessarticlenow.com");
     
-    let body = CLIENT.get(&proxy_url).send().await?.text().await?;
-    let document = Html::parse_document(&body);
+    let body = http::fetcher().get_text(&proxy_url, opts).await?;
+    let article = parse_article(url, api_title, &body);
 
-    // Extract title
-    let title_selector = Selector::parse(r#"h1[data-testid="headline"]"#)
-        .or_else(|_| Selector::parse("h1.css-88wicj"))
-        .or_else(|_| Selector::parse("h1"))
-        .unwrap();
-    
-    let scraped_title = document
-        .select(&title_selector)
-        .next()
-        .map(|el| el.text().collect::<String>().trim().to_string())
+    if article.is_none() {
+        debug!(
+            preview = %body.chars().take(600).collect::<String>().replace('\n', " "),
+            "No article content parsed; HTML preview"
+        );
+    }
+    Ok(article)
+}
+
+/// Extract title, publication date and body from a proxied NYT article page,
+/// falling back to `api_title` when the page has no headline
+pub fn parse_article(url: &str, api_title: &str, html: &str) -> Option<NewsArticle> {
+    let document = Html::parse_document(html);
+
+    // Extract title (first selector with any text wins)
+    let scraped_title = [r#"h1[data-testid="headline"]"#, "h1.css-88wicj", "h1"]
+        .iter()
+        .filter_map(|css| Selector::parse(css).ok())
+        .find_map(|sel| {
+            document
+                .select(&sel)
+                .next()
+                .map(|el| el.text().collect::<String>().trim().to_string())
+                .filter(|t| !t.is_empty())
+        })
         .unwrap_or_default();
 
     // Use API title as fallback if scraped title is empty or blank
@@ -149,24 +205,23 @@ async fn fetch_article(url: &str, api_title: &str) -> Result<Option<NewsArticle>
     debug!(title = %title, "Final title");
 
     // Extract published date
-    let time_selector = Selector::parse(r#"time[datetime]"#).unwrap();
-    let published_date = if let Some(el) = document.select(&time_selector).next() {
-        if let Some(datetime) = el.value().attr("datetime") {
-            datetime.to_string()
-        } else {
-            // Fallback: try to get the text content of the time element
-            el.text()
-                .collect::<String>()
-                .trim()
-                .split_whitespace()
-                .collect::<Vec<_>>()
-                .join(" ")
-        }
+    // Prefer the machine-readable attribute; the first <time>'s text is the fallback
+    let dated_selector = Selector::parse("time[datetime]").unwrap();
+    let time_selector = Selector::parse("time").unwrap();
+    let published_date = if let Some(datetime) = document.select(&dated_selector).find_map(|el| el.value().attr("datetime")) {
+        datetime.to_string()
+    } else if let Some(el) = document.select(&time_selector).next() {
+        el.text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
     } else {
         "Date not found".to_string()
     };
 
-    debug!(published_date = %published_date, "Extracted published date");
+    let published_at = common::parse_rfc3339(&published_date);
+    debug!(published_date = %published_date, parsed = published_at.is_some(), "Extracted published date");
 
     // Add title and date at the top
     let mut content = String::new();
@@ -268,17 +323,10 @@ async fn fetch_article(url: &str, api_title: &str) -> Result<Option<NewsArticle>
     let len = content.len();
     info!(bytes = len, "Parsed NYT article");
 
-    if len > 200 {
-        // Ensure we have substantial content
-        Ok(Some(NewsArticle {
-            source: url.to_string(),
-            content,
-        }))
-    } else {
-        debug!(
-            preview = %body.chars().take(600).collect::<String>().replace('\n', " "),
-            "No article content parsed; HTML preview"
-        );
-        Ok(None)
-    }
+    // Ensure we have substantial content
+    (len > 200).then(|| NewsArticle {
+        source: url.to_string(),
+        content,
+        published_at,
+    })
 }
@@ -0,0 +1,419 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use tracing::{info, instrument};
+
+/// Options read from config.yaml on top of the aj configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    /// Per-source overrides keyed by `Source::id` (e.g. `bbcnews`)
+    #[serde(default)]
+    pub sources: BTreeMap<String, SourceSettings>,
+    /// Extra RSS 2.0 / Atom driven sources
+    #[serde(default)]
+    pub feeds: Vec<FeedSettings>,
+    /// Extra CSS-selector driven sources
+    #[serde(default)]
+    pub declarative: Vec<DeclarativeSettings>,
+    /// Shared HTTP client behaviour
+    #[serde(default)]
+    pub http: HttpSettings,
+    /// LLM analysis options
+    #[serde(default)]
+    pub analyzer: AnalyzerSettings,
+    /// Grouping of articles about the same event into stories
+    #[serde(default)]
+    pub clustering: ClusteringSettings,
+    /// Comparison with earlier editions
+    #[serde(default)]
+    pub novelty: NoveltySettings,
+    /// Which editions are published when
+    #[serde(default)]
+    pub editions: EditionsSettings,
+}
+
+/// Tuning for a single source; unset values fall back to the scraper's defaults
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceSettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Cap on the total number of indexed articles
+    pub max_articles: Option<usize>,
+    /// Cap on the number of articles taken from each section page
+    pub max_per_section: Option<usize>,
+    /// Number of article pages fetched at once
+    pub concurrency: Option<usize>,
+    /// Index pages to scrape instead of the built-in ones
+    pub section_urls: Option<Vec<String>>,
+    /// Overrides `http.respect_robots` for this source
+    pub respect_robots: Option<bool>,
+}
+
+/// A feed-driven source; `max_per_section` caps the items taken from each feed URL
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedSettings {
+    pub id: String,
+    pub name: Option<String>,
+    pub urls: Vec<String>,
+    #[serde(flatten)]
+    pub source: SourceSettings,
+}
+
+/// A scraper described by CSS selectors; empty selector lists use generic defaults
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeclarativeSettings {
+    pub id: String,
+    pub name: Option<String>,
+    /// List pages that link to articles
+    pub index_urls: Vec<String>,
+    /// Anchor selectors tried in order until `max_per_section` links are found
+    pub link_selectors: Vec<String>,
+    /// Regex article URLs must match; also used to scan the raw HTML as a last resort
+    pub url_pattern: Option<String>,
+    /// Headline selectors; `meta` matches use their `content` attribute
+    #[serde(default)]
+    pub title_selectors: Vec<String>,
+    /// Body selectors; the first one that yields any text wins
+    #[serde(default)]
+    pub body_selectors: Vec<String>,
+    /// `meta` selectors holding an RFC 3339 publication time (JSON-LD is always tried first)
+    #[serde(default)]
+    pub date_selectors: Vec<String>,
+    /// Element whose text is kept as the raw date when nothing parses
+    pub date_text_selector: Option<String>,
+    #[serde(flatten)]
+    pub source: SourceSettings,
+}
+
+/// Politeness and retry options for the shared HTTP fetcher
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Sent with every request; defaults to an honest `awful_text_news/<version>` string
+    pub user_agent: Option<String>,
+    pub timeout_secs: u64,
+    /// Retries after a 429, 5xx, timeout or connection error
+    pub max_retries: u32,
+    /// Requests in flight to a single host
+    pub per_host_concurrency: usize,
+    /// Requests started per second against a single host (unlimited when unset)
+    pub per_host_rps: Option<f64>,
+    /// Skip URLs disallowed by robots.txt and honor Crawl-delay
+    pub respect_robots: bool,
+    /// Keep fetched pages on disk and revalidate them with ETag / Last-Modified
+    pub cache: bool,
+    /// Where cached pages live (defaults to `http_cache` next to config.yaml)
+    pub cache_dir: Option<String>,
+}
+
+/// Options for turning articles into summaries
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalyzerSettings {
+    /// Reuse earlier analyses of identical article text
+    pub cache: bool,
+    /// Where analyses are kept (defaults to `analysis_cache` next to config.yaml)
+    pub cache_dir: Option<String>,
+    /// Article tokens per request before chunking; by default `context_max_tokens`
+    /// minus `assistant_minimum_context_tokens` and the template's prompt
+    pub max_input_tokens: Option<usize>,
+    /// How many times to re-ask with the validation errors when a response breaks the schema
+    pub max_reasks: usize,
+}
+
+/// Options for grouping an edition's articles into stories
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClusteringSettings {
+    pub enabled: bool,
+    /// Combined title/summary, entity and tag similarity (0 to 1) at which articles are merged
+    pub threshold: f64,
+}
+
+/// Options for marking articles as new, updated or carried over from earlier editions
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NoveltySettings {
+    pub enabled: bool,
+    /// Days of editions to compare against, counting the current day
+    pub lookback_days: u32,
+    /// Leave stories whose articles are all carried over unchanged out of the Markdown edition
+    pub hide_carried_over: bool,
+}
+
+/// The edition schedule; without one, morning/afternoon/evening from 00:00/08:00/16:00
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EditionsSettings {
+    /// IANA name such as `America/New_York`; the host's local time when unset
+    pub time_zone: Option<String>,
+    pub schedule: Vec<EditionSettings>,
+}
+
+/// One edition; it runs from `starts` until the next edition starts
+#[derive(Debug, Clone, Deserialize)]
+pub struct EditionSettings {
+    /// Used in file names, so letters, digits, `-` and `_` only
+    pub name: String,
+    /// Heading in the indexes; the name capitalized when unset
+    pub label: Option<String>,
+    /// Local `HH:MM`; leave out for editions only run with `--edition`
+    pub starts: Option<String>,
+    /// Weekdays (e.g. `[sat, sun]`) it starts on; every day when empty
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for SourceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_articles: None,
+            max_per_section: None,
+            concurrency: None,
+            section_urls: None,
+            respect_robots: None,
+        }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            timeout_secs: 30,
+            max_retries: 3,
+            per_host_concurrency: 4,
+            per_host_rps: Some(2.0),
+            respect_robots: true,
+            cache: true,
+            cache_dir: None,
+        }
+    }
+}
+
+impl Default for AnalyzerSettings {
+    fn default() -> Self {
+        Self {
+            cache: true,
+            cache_dir: None,
+            max_input_tokens: None,
+            max_reasks: 1,
+        }
+    }
+}
+
+impl Default for ClusteringSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: crate::clustering::DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+impl Default for NoveltySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lookback_days: 1,
+            hide_carried_over: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Settings for a source, or the defaults if it is not configured
+    pub fn source(&self, id: &str) -> SourceSettings {
+        self.sources.get(id).cloned().unwrap_or_default()
+    }
+}
+
+impl SourceSettings {
+    pub fn max_articles_or(&self, default: usize) -> usize {
+        self.max_articles.unwrap_or(default)
+    }
+
+    pub fn max_per_section_or(&self, default: usize) -> usize {
+        self.max_per_section.unwrap_or(default)
+    }
+
+    pub fn concurrency_or(&self, default: usize) -> usize {
+        self.concurrency.unwrap_or(default).max(1)
+    }
+
+    pub fn section_urls_or(&self, default: &[&str]) -> Vec<String> {
+        match &self.section_urls {
+            Some(urls) => urls.clone(),
+            None => default.iter().map(|u| u.to_string()).collect(),
+        }
+    }
+}
+
+/// Load the settings from a YAML config file, ignoring the aj-specific keys
+#[instrument(level = "info")]
+pub fn load_settings(path: &str) -> Result<Settings, Box<dyn Error>> {
+    let yaml = std::fs::read_to_string(path)?;
+    let settings = parse_settings(&yaml)?;
+    info!(sources = settings.sources.len(), "Loaded source settings");
+    Ok(settings)
+}
+
+/// Parse settings from YAML text
+pub fn parse_settings(yaml: &str) -> Result<Settings, Box<dyn Error>> {
+    let settings: Option<Settings> = serde_yaml::from_str(yaml)?;
+    Ok(settings.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_settings_ignores_aj_keys() {
+        let yaml = r#"
+api_key:
+api_base: http://localhost:8080/v1
+model: Qwen_Qwen2.5-3B-Instruct-GGUF
+context_max_tokens: 8092
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        assert!(settings.sources.is_empty());
+        assert!(settings.source("cnn").enabled);
+    }
+
+    #[test]
+    fn test_parse_source_overrides() {
+        let yaml = r#"
+sources:
+  bbcnews:
+    enabled: false
+  aljazeera:
+    max_articles: 10
+    concurrency: 2
+    section_urls:
+      - https://www.aljazeera.com/news/
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        assert!(!settings.source("bbcnews").enabled);
+
+        let aj = settings.source("aljazeera");
+        assert!(aj.enabled);
+        assert_eq!(aj.max_articles_or(60), 10);
+        assert_eq!(aj.max_per_section_or(20), 20);
+        assert_eq!(aj.concurrency_or(8), 2);
+        assert_eq!(
+            aj.section_urls_or(&["https://example.com"]),
+            vec!["https://www.aljazeera.com/news/".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_source_defaults() {
+        let settings = SourceSettings::default();
+        assert_eq!(settings.max_articles_or(30), 30);
+        assert_eq!(settings.concurrency_or(4), 4);
+        assert_eq!(settings.section_urls_or(&["https://lite.cnn.com"]), vec!["https://lite.cnn.com"]);
+    }
+
+    #[test]
+    fn test_concurrency_is_at_least_one() {
+        let settings = SourceSettings {
+            concurrency: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(settings.concurrency_or(8), 1);
+    }
+
+    #[test]
+    fn test_parse_feeds() {
+        let yaml = r#"
+feeds:
+  - id: example_science
+    urls:
+      - https://example.org/science.atom
+    max_per_section: 5
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        assert_eq!(settings.feeds.len(), 1);
+        let feed = &settings.feeds[0];
+        assert_eq!(feed.id, "example_science");
+        assert_eq!(feed.name, None);
+        assert!(feed.source.enabled);
+        assert_eq!(feed.source.max_per_section_or(20), 5);
+    }
+
+    #[test]
+    fn test_parse_declarative() {
+        let yaml = r#"
+declarative:
+  - id: example
+    index_urls:
+      - https://www.example.com/world
+    link_selectors:
+      - "a.story-link[href]"
+    concurrency: 2
+"#;
+        let settings = parse_settings(yaml).unwrap();
+        assert_eq!(settings.declarative.len(), 1);
+        let def = &settings.declarative[0];
+        assert_eq!(def.id, "example");
+        assert_eq!(def.link_selectors, vec!["a.story-link[href]"]);
+        assert!(def.url_pattern.is_none());
+        assert!(def.body_selectors.is_empty());
+        assert_eq!(def.source.concurrency_or(4), 2);
+    }
+
+    #[test]
+    fn test_parse_http() {
+        let yaml = r#"
+http:
+  user_agent: "example-bot/1.0"
+  per_host_rps: 0.5
+"#;
+        let http = parse_settings(yaml).unwrap().http;
+        assert_eq!(http.user_agent.as_deref(), Some("example-bot/1.0"));
+        assert_eq!(http.per_host_rps, Some(0.5));
+        assert_eq!(http.max_retries, 3);
+        assert_eq!(http.per_host_concurrency, 4);
+        assert!(http.respect_robots);
+        assert!(http.cache);
+        assert!(http.cache_dir.is_none());
+    }
+
+    #[test]
+    fn test_parse_analyzer() {
+        let settings = parse_settings("analyzer:\n  cache: false\n").unwrap();
+        assert!(!settings.analyzer.cache);
+        assert_eq!(settings.analyzer.max_reasks, 1);
+        assert!(parse_settings("").unwrap().analyzer.cache);
+        assert_eq!(parse_settings("analyzer:\n  max_reasks: 0\n").unwrap().analyzer.max_reasks, 0);
+    }
+
+    #[test]
+    fn test_parse_clustering() {
+        let clustering = parse_settings("clustering:\n  threshold: 0.6\n").unwrap().clustering;
+        assert!(clustering.enabled);
+        assert_eq!(clustering.threshold, 0.6);
+        assert!(!parse_settings("clustering:\n  enabled: false\n").unwrap().clustering.enabled);
+    }
+
+    #[test]
+    fn test_parse_novelty() {
+        let novelty = parse_settings("novelty:\n  lookback_days: 2\n  hide_carried_over: true\n").unwrap().novelty;
+        assert!(novelty.enabled);
+        assert_eq!(novelty.lookback_days, 2);
+        assert!(novelty.hide_carried_over);
+        assert!(!parse_settings("").unwrap().novelty.hide_carried_over);
+    }
+
+    #[test]
+    fn test_empty_yaml() {
+        let settings = parse_settings("").unwrap();
+        assert!(settings.sources.is_empty());
+    }
+}
@@ -1,28 +1,9 @@
-use chrono::{Local, NaiveTime};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::error::Error;
 use std::fs as stdfs;
 use tokio::fs;
-use tracing::{info, instrument, warn};
-
-/// Classify current time into morning/afternoon/evening
-#[instrument]
-pub fn time_of_day() -> String {
-    let morning_low = NaiveTime::from_hms_opt(0, 00, 0).unwrap();
-    let morning_high = NaiveTime::from_hms_opt(8, 00, 0).unwrap();
-    let afternoon_low = NaiveTime::from_hms_opt(8, 00, 0).unwrap();
-    let afternoon_high = NaiveTime::from_hms_opt(16, 00, 0).unwrap();
-
-    let tod = Local::now().time();
-    let which = if (tod >= morning_low) && (tod < morning_high) {
-        "morning"
-    } else if (tod >= afternoon_low) && (tod < afternoon_high) {
-        "afternoon"
-    } else {
-        "evening"
-    };
-    tracing::debug!(%tod, %which, "Computed time_of_day");
-    which.to_string()
-}
+use tracing::{info, instrument};
 
 /// Truncate a string for logging purposes
 pub fn truncate_for_log(s: &str, max: usize) -> String {
@@ -56,6 +37,13 @@ pub fn upcase(s: &str) -> String {
     }
 }
 
+/// Stable 64-bit FNV-1a hash (unlike `DefaultHasher`, identical across runs and builds)
+pub fn stable_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
 /// Ensure a directory exists and is writable (create + touch + delete).
 #[instrument(level = "info", skip_all, fields(path = %path))]
 pub async fn ensure_writable_dir(path: &str) -> Result<(), Box<dyn Error>> {
@@ -74,10 +62,46 @@ pub async fn ensure_writable_dir(path: &str) -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Replace `path` via a temp file + rename so readers never see a half-written file
+pub async fn write_atomic(path: &str, contents: impl AsRef<[u8]>) -> Result<(), Box<dyn Error>> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, contents).await?;
+    fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Write `items` as newline-delimited JSON, one item per line
+#[instrument(level = "info", skip_all, fields(path = %path))]
+pub async fn write_ndjson<T: Serialize>(path: &str, items: &[T]) -> Result<(), Box<dyn Error>> {
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&serde_json::to_string(item)?);
+        out.push('\n');
+    }
+    fs::write(path, out).await?;
+    info!(count = items.len(), "Wrote NDJSON");
+    Ok(())
+}
+
+/// Read newline-delimited JSON written by `write_ndjson`; blank lines are ignored
+#[instrument(level = "info", skip_all, fields(path = %path))]
+pub async fn read_ndjson<T: DeserializeOwned>(path: &str) -> Result<Vec<T>, Box<dyn Error>> {
+    let text = fs::read_to_string(path).await?;
+    let mut items = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let item = serde_json::from_str(line).map_err(|e| format!("{}:{}: {}", path, i + 1, e))?;
+        items.push(item);
+    }
+    info!(count = items.len(), "Read NDJSON");
+    Ok(items)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveTime;
 
     #[test]
     fn test_truncate_for_log_short_string() {
@@ -116,31 +140,6 @@ mod tests {
         assert_eq!(upcase("a"), "A");
     }
 
-    #[test]
-    fn test_time_of_day_morning() {
-        // We can't easily test the actual time_of_day function without mocking time,
-        // but we can test the logic by checking specific times
-        let morning = NaiveTime::from_hms_opt(6, 30, 0).unwrap();
-        let morning_low = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
-        let morning_high = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
-        assert!(morning >= morning_low && morning < morning_high);
-    }
-
-    #[test]
-    fn test_time_of_day_afternoon() {
-        let afternoon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
-        let afternoon_low = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
-        let afternoon_high = NaiveTime::from_hms_opt(16, 0, 0).unwrap();
-        assert!(afternoon >= afternoon_low && afternoon < afternoon_high);
-    }
-
-    #[test]
-    fn test_time_of_day_evening() {
-        let evening = NaiveTime::from_hms_opt(20, 0, 0).unwrap();
-        let afternoon_high = NaiveTime::from_hms_opt(16, 0, 0).unwrap();
-        assert!(evening >= afternoon_high);
-    }
-
     #[test]
     fn test_looks_truncated() {
         // Test EOF detection
@@ -150,4 +149,39 @@ mod tests {
             assert!(looks_truncated(&e));
         }
     }
+
+    #[test]
+    fn test_stable_hash() {
+        assert_eq!(stable_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(stable_hash(b"https://lite.cnn.com"), stable_hash(b"https://text.npr.org"));
+    }
+
+    #[tokio::test]
+    async fn test_write_atomic_replaces_file() {
+        let path = std::env::temp_dir().join(format!("atn_atomic_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_atomic(path, "first").await.unwrap();
+        write_atomic(path, "second").await.unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "second");
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_round_trip() {
+        let path = std::env::temp_dir().join(format!("atn_ndjson_{}.ndjson", std::process::id()));
+        let path = path.to_str().unwrap();
+        let items = vec![serde_json::json!({"source": "a", "content": "one\ntwo"}), serde_json::json!({"n": 2})];
+        write_ndjson(path, &items).await.unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 2);
+
+        let read: Vec<serde_json::Value> = read_ndjson(path).await.unwrap();
+        assert_eq!(read, items);
+
+        std::fs::write(path, "{\"n\": 1}\n\nnot json\n").unwrap();
+        let err = read_ndjson::<serde_json::Value>(path).await.unwrap_err();
+        assert!(err.to_string().contains(":3:"), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,96 @@
+use awful_text_news::analyzer::Analyzer;
+use awful_text_news::http::{self, cache::CachedResponse, cache::HttpCache};
+use awful_text_news::mock_llm::{MockLlm, news_parser_template};
+use awful_text_news::models::{FrontPage, NewsArticle};
+use awful_text_news::outputs::{JsonOutput, MarkdownOutput, Output};
+use awful_text_news::utils::{ensure_writable_dir, read_ndjson, write_atomic, write_ndjson};
+use awful_text_news::{Pipeline, scrapers, settings};
+use std::path::Path;
+
+/// Only NPR, so the snapshot needs nothing but its front page and one article
+const SETTINGS: &str = "
+sources:
+  cnn: { enabled: false }
+  apnews: { enabled: false }
+  aljazeera: { enabled: false }
+  bbcnews: { enabled: false }
+  nyt: { enabled: false }
+  npr: { max_articles: 3 }
+";
+
+fn fixture(path: &str) -> String {
+    std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)).unwrap()
+}
+
+/// A `--record` snapshot holding NPR's front page and one of the three articles it links to
+async fn record_snapshot(dir: &Path) {
+    let snapshot = HttpCache::new(dir);
+    for (url, page) in [
+        ("https://text.npr.org", "npr/index_synthetic.html"),
+        ("https://text.npr.org/nx-s1-5388771", "npr/article_synthetic.html"),
+    ] {
+        snapshot
+            .store(&CachedResponse {
+                url: url.to_string(),
+                final_url: url.to_string(),
+                etag: None,
+                last_modified: None,
+                fetched_at: "2025-05-06T14:30:00+00:00".to_string(),
+                body: fixture(page),
+            })
+            .await
+            .unwrap();
+    }
+}
+
+/// `fetch --replay`, then `analyze` against the mock LLM, then `render` and `publish`,
+/// with the same files between stages as the CLI
+#[tokio::test]
+async fn test_fetch_analyze_render() {
+    let dir = std::env::temp_dir().join(format!("atn_end_to_end_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    record_snapshot(&dir.join("snapshot")).await;
+
+    // fetch
+    let settings = settings::parse_settings(SETTINGS).unwrap();
+    http::init(settings.http.clone(), http::Mode::Replay(dir.join("snapshot")));
+    let fetched = scrapers::fetch_all(&scrapers::registry(&settings, None)).await;
+    write_ndjson(&path("articles.ndjson"), &fetched).await.unwrap();
+
+    // analyze
+    let mock = MockLlm::echo_articles().await;
+    let articles: Vec<NewsArticle> = read_ndjson(&path("articles.ndjson")).await.unwrap();
+    assert_eq!(articles.len(), 1, "only the recorded article can be fetched");
+    let front_page = Pipeline::new(Analyzer::new(mock.config(), news_parser_template()))
+        .analyze_edition(articles)
+        .await;
+    write_atomic(&path("front_page.json"), serde_json::to_string_pretty(&front_page).unwrap())
+        .await
+        .unwrap();
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].user().contains("has climbed past 900"));
+
+    // render and publish
+    let front_page: FrontPage = serde_json::from_str(&std::fs::read_to_string(path("front_page.json")).unwrap()).unwrap();
+    assert_eq!(front_page.articles.len(), 1);
+    assert_eq!(front_page.articles[0].source.as_deref(), Some("https://text.npr.org/nx-s1-5388771"));
+    ensure_writable_dir(&path("json")).await.unwrap();
+    ensure_writable_dir(&path("markdown")).await.unwrap();
+    JsonOutput::new(path("json")).write(&front_page).await.unwrap();
+    let markdown = MarkdownOutput::new(path("markdown"));
+    markdown.write_page(&front_page).await.unwrap();
+    markdown.update_indexes(&front_page).await.unwrap();
+
+    let json = dir.join("json").join(&front_page.local_date).join(format!("{}.json", front_page.time_of_day));
+    assert!(json.exists(), "{} missing", json.display());
+    let page_name = MarkdownOutput::page_filename(&front_page);
+    let page = std::fs::read_to_string(dir.join("markdown").join(&page_name)).unwrap();
+    assert!(page.contains(&front_page.articles[0].title));
+    assert!(page.contains("https://text.npr.org/nx-s1-5388771"));
+    let summary = std::fs::read_to_string(dir.join("markdown").join("SUMMARY.md")).unwrap();
+    assert!(summary.contains(&format!("](./{})", page_name)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}